@@ -0,0 +1,503 @@
+//! Temporal properties checked on the simulation dumps, since iverilog only has limited support
+//! for SystemVerilog assertions.
+
+mod parser;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+pub use parser::{parse, parse_expr, BinaryOp, Expr, Property, Sequence};
+
+use crate::{decoders::Signal, error::Error, icarus::vcd::VCDFile, state::AppState, waves::dump};
+
+/// Property declared in the project file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assertion {
+    pub name: String,
+    /// Path of the clock on which the signals are sampled
+    pub clock: String,
+    /// Sample on the falling edges of the clock instead of the rising ones
+    #[serde(default)]
+    pub falling_edge: bool,
+    pub property: String,
+    /// Condition disabling the property, e.g. a reset
+    #[serde(default)]
+    pub disable: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    /// Time of the clock edge at which the failing attempt started
+    pub start: u32,
+    /// Time of the clock edge at which the failure was detected
+    pub time: u32,
+    /// Values of the signals of the property at `time`, in hexadecimal
+    pub values: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum AssertionOutcome {
+    Passed {
+        cycles: usize,
+    },
+    Failed {
+        cycles: usize,
+        failures: Vec<Failure>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionReport {
+    pub name: String,
+    #[serde(flatten)]
+    pub outcome: AssertionOutcome,
+}
+
+/// Values of a signal, sampled on each clock edge
+#[derive(Debug)]
+struct Samples {
+    width: u32,
+    values: Vec<Option<u64>>,
+}
+
+fn signals_of_expr<'a>(expr: &'a Expr, acc: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Signal(path) => {
+            if !acc.contains(&path.as_str()) {
+                acc.push(path)
+            }
+        }
+        Expr::Const(_) => {}
+        Expr::Not(e) | Expr::BitNot(e) | Expr::Neg(e) => signals_of_expr(e, acc),
+        Expr::Rose(e) | Expr::Fell(e) | Expr::Stable(e) => signals_of_expr(e, acc),
+        Expr::Binary(_, lhs, rhs) => {
+            signals_of_expr(lhs, acc);
+            signals_of_expr(rhs, acc);
+        }
+    }
+}
+
+fn signals_of_sequence<'a>(sequence: &'a Sequence, acc: &mut Vec<&'a str>) {
+    match sequence {
+        Sequence::Expr(e) | Sequence::Eventually(_, e) => signals_of_expr(e, acc),
+        Sequence::Concat { first, second, .. } => {
+            signals_of_sequence(first, acc);
+            signals_of_sequence(second, acc);
+        }
+        Sequence::Throughout(e, s) => {
+            signals_of_expr(e, acc);
+            signals_of_sequence(s, acc);
+        }
+    }
+}
+
+fn mask(value: u64, width: u32) -> u64 {
+    if width >= 64 {
+        value
+    } else {
+        value & ((1 << width) - 1)
+    }
+}
+
+struct Checker<'a> {
+    samples: &'a HashMap<&'a str, Samples>,
+    cycles: usize,
+    /// Whether a match was cut by the end of the dump
+    incomplete: bool,
+    /// Last cycle looked at while matching
+    furthest: usize,
+}
+
+impl Checker<'_> {
+    /// Value and width of an expression at a cycle, `None` if it has unknown bits
+    fn eval(&self, expr: &Expr, cycle: usize) -> Option<(u64, u32)> {
+        let bool = |b: bool| Some((b as u64, 1));
+
+        match expr {
+            Expr::Signal(path) => {
+                let samples = &self.samples[path.as_str()];
+                samples.values[cycle].map(|v| (v, samples.width))
+            }
+            Expr::Const(value) => Some((*value, 32)),
+            Expr::Not(e) => self.eval(e, cycle).and_then(|(v, _)| bool(v == 0)),
+            Expr::BitNot(e) => self.eval(e, cycle).map(|(v, w)| (mask(!v, w), w)),
+            Expr::Neg(e) => self
+                .eval(e, cycle)
+                .map(|(v, w)| (mask(v.wrapping_neg(), w), w)),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                let lhs = self.eval(lhs, cycle).map(|(v, _)| v != 0);
+                let rhs = self.eval(rhs, cycle).map(|(v, _)| v != 0);
+                // A known operand can decide the result even if the other one is unknown
+                let absorbing = *op == BinaryOp::Or;
+                match (lhs, rhs) {
+                    (Some(l), _) if l == absorbing => bool(absorbing),
+                    (_, Some(r)) if r == absorbing => bool(absorbing),
+                    (Some(_), Some(_)) => bool(!absorbing),
+                    _ => None,
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (l, lw) = self.eval(lhs, cycle)?;
+                let (r, rw) = self.eval(rhs, cycle)?;
+                let width = lw.max(rw);
+                match op {
+                    BinaryOp::BitOr => Some((l | r, width)),
+                    BinaryOp::BitXor => Some((l ^ r, width)),
+                    BinaryOp::BitAnd => Some((l & r, width)),
+                    BinaryOp::Eq => bool(l == r),
+                    BinaryOp::Ne => bool(l != r),
+                    BinaryOp::Lt => bool(l < r),
+                    BinaryOp::Le => bool(l <= r),
+                    BinaryOp::Gt => bool(l > r),
+                    BinaryOp::Ge => bool(l >= r),
+                    BinaryOp::Add => Some((mask(l.wrapping_add(r), width), width)),
+                    BinaryOp::Sub => Some((mask(l.wrapping_sub(r), width), width)),
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                }
+            }
+            Expr::Rose(e) | Expr::Fell(e) => {
+                let target = matches!(expr, Expr::Rose(_)) as u64;
+                let level = |c: usize| self.eval(e, c).map(|(v, _)| v & 1);
+                bool(cycle > 0 && level(cycle) == Some(target) && level(cycle - 1) != Some(target))
+            }
+            Expr::Stable(e) => bool(cycle == 0 || self.eval(e, cycle) == self.eval(e, cycle - 1)),
+        }
+    }
+
+    fn holds(&self, expr: &Expr, cycle: usize) -> bool {
+        self.eval(expr, cycle).is_some_and(|(v, _)| v != 0)
+    }
+
+    /// Cycles at which the matches of `sequence` starting at `cycle` end
+    fn ends(&mut self, sequence: &Sequence, cycle: usize) -> Vec<usize> {
+        if cycle >= self.cycles {
+            self.incomplete = true;
+            return vec![];
+        }
+        self.furthest = self.furthest.max(cycle);
+
+        match sequence {
+            Sequence::Expr(e) => {
+                if self.holds(e, cycle) {
+                    vec![cycle]
+                } else {
+                    vec![]
+                }
+            }
+            Sequence::Concat {
+                first,
+                min,
+                max,
+                second,
+            } => {
+                let mut ends = vec![];
+                for end in self.ends(first, cycle) {
+                    for delay in *min..=*max {
+                        ends.extend(self.ends(second, end + delay as usize));
+                    }
+                }
+                ends.sort();
+                ends.dedup();
+                ends
+            }
+            Sequence::Throughout(condition, sequence) => {
+                let ends = self.ends(sequence, cycle);
+                ends.into_iter()
+                    .filter(|end| (cycle..=*end).all(|c| self.holds(condition, c)))
+                    .collect()
+            }
+            Sequence::Eventually(cycles, e) => {
+                for c in cycle..=cycle + *cycles as usize {
+                    if c >= self.cycles {
+                        self.incomplete = true;
+                        break;
+                    }
+                    self.furthest = self.furthest.max(c);
+                    if self.holds(e, c) {
+                        return vec![c];
+                    }
+                }
+                vec![]
+            }
+        }
+    }
+
+    /// Checks the attempt of the property starting at `cycle`, returning the cycles at which it
+    /// fails. Attempts cut by the end of the dump do not fail.
+    fn check(&mut self, property: &Property, cycle: usize) -> Vec<usize> {
+        self.furthest = cycle;
+        self.incomplete = false;
+
+        match property {
+            Property::Sequence(sequence) => {
+                if self.ends(sequence, cycle).is_empty() && !self.incomplete {
+                    vec![self.furthest]
+                } else {
+                    vec![]
+                }
+            }
+            Property::Implication {
+                antecedent,
+                consequent,
+                overlapping,
+            } => {
+                let mut failures = vec![];
+                for end in self.ends(antecedent, cycle) {
+                    let start = if *overlapping { end } else { end + 1 };
+                    self.furthest = start.min(self.cycles.saturating_sub(1));
+                    self.incomplete = false;
+                    if self.ends(consequent, start).is_empty() && !self.incomplete {
+                        failures.push(self.furthest);
+                    }
+                }
+                failures
+            }
+        }
+    }
+}
+
+/// Checks an assertion on a dump
+pub fn check(vcd: &VCDFile, assertion: &Assertion) -> Result<AssertionOutcome, String> {
+    let property = parse(&assertion.property)?;
+    let disable = assertion.disable.as_deref().map(parse_expr).transpose()?;
+
+    let mut paths = vec![];
+    match &property {
+        Property::Sequence(s) => signals_of_sequence(s, &mut paths),
+        Property::Implication {
+            antecedent,
+            consequent,
+            ..
+        } => {
+            signals_of_sequence(antecedent, &mut paths);
+            signals_of_sequence(consequent, &mut paths);
+        }
+    }
+    let reported = paths.len();
+    if let Some(disable) = &disable {
+        signals_of_expr(disable, &mut paths);
+    }
+
+    let edges = Signal::from_vcd(vcd, &assertion.clock)
+        .map_err(|e| format!("{e:?}"))?
+        .edges()
+        .filter(|(_, level)| *level != assertion.falling_edge)
+        .map(|(time, _)| time)
+        .collect::<Vec<_>>();
+
+    let mut samples = HashMap::new();
+    for path in &paths {
+        let variable = vcd
+            .find_variable(path)
+            .ok_or_else(|| format!("Unknown signal {path}"))?;
+        let signal = Signal::from_vcd(vcd, path).map_err(|e| format!("{e:?}"))?;
+        samples.insert(
+            *path,
+            Samples {
+                width: variable.size,
+                // Signals are sampled as seen by the flip-flops, before the changes of the edge
+                values: edges.iter().map(|e| signal.value_before(*e)).collect(),
+            },
+        );
+    }
+
+    let mut checker = Checker {
+        samples: &samples,
+        cycles: edges.len(),
+        incomplete: false,
+        furthest: 0,
+    };
+    let mut failures = vec![];
+    for cycle in 0..edges.len() {
+        for end in checker.check(&property, cycle) {
+            if disable
+                .as_ref()
+                .is_some_and(|d| (cycle..=end).any(|c| checker.holds(d, c)))
+            {
+                continue;
+            }
+
+            failures.push(Failure {
+                start: edges[cycle],
+                time: edges[end],
+                values: paths[..reported]
+                    .iter()
+                    .map(|path| {
+                        let value = samples[path].values[end];
+                        (
+                            path.to_string(),
+                            value.map_or("x".to_owned(), |v| format!("{v:x}")),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    Ok(if failures.is_empty() {
+        AssertionOutcome::Passed {
+            cycles: edges.len(),
+        }
+    } else {
+        AssertionOutcome::Failed {
+            cycles: edges.len(),
+            failures,
+        }
+    })
+}
+
+/// Checks an assertion, reporting errors (e.g. syntax errors) in the outcome
+pub fn report(vcd: &VCDFile, assertion: &Assertion) -> AssertionReport {
+    AssertionReport {
+        name: assertion.name.clone(),
+        outcome: check(vcd, assertion)
+            .unwrap_or_else(|message| AssertionOutcome::Error { message }),
+    }
+}
+
+/// Checks an assertion on the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn check_assertion(
+    file: usize,
+    assertion: Assertion,
+    state: AppState<'_>,
+) -> Result<AssertionReport, Error> {
+    let state = state.lock().unwrap();
+    Ok(report(dump(&state, file)?, &assertion))
+}
+
+/// Checks the assertions declared in the project on the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn check_assertions(file: usize, state: AppState<'_>) -> Result<Vec<AssertionReport>, Error> {
+    let state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?;
+    let vcd = dump(&state, file)?;
+
+    Ok(project
+        .assertions
+        .iter()
+        .map(|assertion| report(vcd, assertion))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    /// Clock of period 10 with rising edges at 10, 20, ..., and signals changing 1 after an edge
+    fn vcd() -> VCDFile {
+        let mut changes = String::new();
+
+        // Cycle at which each signal goes high (and stays high for one cycle)
+        let req = [1, 5, 8];
+        let ack = [3, 6];
+        for cycle in 0..12u32 {
+            let time = cycle * 10;
+            changes.push_str(&format!("#{time}\n1!\n"));
+            changes.push_str(&format!("#{}\n", time + 1));
+            changes.push_str(&format!("{}\"\n", req.contains(&cycle) as u8));
+            changes.push_str(&format!("{}#\n", ack.contains(&cycle) as u8));
+            changes.push_str(&format!("b{:b} $\n", cycle % 16));
+            changes.push_str(&format!("{}%\n", (cycle == 0) as u8));
+            changes.push_str(&format!("#{}\n0!\n", time + 5));
+        }
+
+        test_dump(
+            "tb",
+            &[
+                "wire 1 ! clk",
+                "wire 1 \" req",
+                "wire 1 # ack",
+                "wire 4 $ count",
+                "wire 1 % rst",
+            ],
+            &changes,
+        )
+    }
+
+    fn assertion(property: &str) -> Assertion {
+        Assertion {
+            name: "test".to_owned(),
+            clock: "tb.clk".to_owned(),
+            falling_edge: false,
+            property: property.to_owned(),
+            disable: None,
+        }
+    }
+
+    fn failure_times(outcome: AssertionOutcome) -> Vec<u32> {
+        match outcome {
+            AssertionOutcome::Passed { .. } => vec![],
+            AssertionOutcome::Failed { failures, .. } => failures.iter().map(|f| f.time).collect(),
+            AssertionOutcome::Error { message } => panic!("{message}"),
+        }
+    }
+
+    #[test]
+    fn implication() {
+        // req is sampled high on the edges at 20, 60 and 90, the last one is never acknowledged
+        let outcome = check(&vcd(), &assertion("tb.req |-> ##[1:2] tb.ack")).unwrap();
+        assert_eq!(failure_times(outcome.clone()), vec![110]);
+        let AssertionOutcome::Failed { failures, .. } = outcome else {
+            unreachable!()
+        };
+        assert_eq!(
+            failures[0].values,
+            vec![
+                ("tb.req".to_owned(), "0".to_owned()),
+                ("tb.ack".to_owned(), "0".to_owned())
+            ]
+        );
+
+        let outcome = check(&vcd(), &assertion("tb.req |=> eventually [3] tb.ack")).unwrap();
+        assert!(failure_times(outcome).is_empty());
+    }
+
+    #[test]
+    fn system_functions() {
+        let outcome = check(&vcd(), &assertion("$rose(tb.req) |=> $fell(tb.req)")).unwrap();
+        assert!(failure_times(outcome).is_empty());
+
+        let outcome = check(&vcd(), &assertion("$stable(tb.count)")).unwrap();
+        assert_eq!(failure_times(outcome).len(), 10);
+    }
+
+    #[test]
+    fn throughout_and_disable() {
+        let outcome = check(
+            &vcd(),
+            &assertion("tb.count == 1 |-> tb.count < 4 throughout (1 ##2 tb.ack)"),
+        )
+        .unwrap();
+        assert!(failure_times(outcome).is_empty());
+        let outcome = check(
+            &vcd(),
+            &assertion("tb.count == 1 |-> tb.count < 3 throughout (1 ##2 tb.ack)"),
+        )
+        .unwrap();
+        assert_eq!(failure_times(outcome), vec![40]);
+
+        let mut counter = assertion("tb.count != 0");
+        assert_eq!(failure_times(check(&vcd(), &counter).unwrap()), vec![10]);
+        counter.disable = Some("tb.rst".to_owned());
+        assert!(failure_times(check(&vcd(), &counter).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            report(&vcd(), &assertion("tb.req |->")).outcome,
+            AssertionOutcome::Error { .. }
+        ));
+        assert!(check(&vcd(), &assertion("tb.missing")).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! Parser of the temporal properties, e.g. `req && !busy |=> ##[1:3] ack`.
+//!
+//! ```text
+//! property := sequence [ ("|->" | "|=>") sequence ]
+//! sequence := [ "##" delay ] item { "##" delay item }
+//! delay    := number | "[" number ":" number "]"
+//! item     := "eventually" "[" number "]" expr
+//!           | "(" sequence ")"
+//!           | expr [ "throughout" item ]
+//! expr     := Verilog-like expression over signals, with `$rose`, `$fell` and `$stable`
+//! ```
+
+use crate::verilog::parse_number;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Dotted path of a signal
+    Signal(String),
+    Const(u64),
+    Not(Box<Expr>),
+    BitNot(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Rose(Box<Expr>),
+    Fell(Box<Expr>),
+    Stable(Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sequence {
+    Expr(Expr),
+    /// `first ##[min:max] second`
+    Concat {
+        first: Box<Sequence>,
+        min: u32,
+        max: u32,
+        second: Box<Sequence>,
+    },
+    /// `condition throughout sequence`
+    Throughout(Expr, Box<Sequence>),
+    /// `eventually [cycles] expr`
+    Eventually(u32, Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    Sequence(Sequence),
+    Implication {
+        antecedent: Sequence,
+        consequent: Sequence,
+        /// `|->` when true, `|=>` (starting on the next cycle) otherwise
+        overlapping: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 23] = [
+    "|->", "|=>", "##", "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "~", "&", "|", "^", "+",
+    "-", "(", ")", "[", "]", ":",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let len = if c.is_ascii_digit() || c == '\'' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '\'' || c == '_'))
+                .unwrap_or(rest.len());
+            let value = parse_number(&rest[..len])
+                .ok_or_else(|| format!("Invalid number {}", &rest[..len]))?;
+            tokens.push(Token::Number(value));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_owned()));
+            len
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(format!("Unexpected character {c}"));
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.accept(op) {
+            Ok(())
+        } else {
+            Err(format!("Expected {op}, found {}", self.describe()))
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(i)) => i.clone(),
+            Some(Token::Number(n)) => n.to_string(),
+            Some(Token::Op(op)) => op.to_string(),
+            None => "end of property".to_owned(),
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        match self.next() {
+            Some(Token::Number(n)) => u32::try_from(n).map_err(|_| format!("{n} is too large")),
+            _ => {
+                self.position -= 1;
+                Err(format!("Expected a number, found {}", self.describe()))
+            }
+        }
+    }
+
+    fn property(&mut self) -> Result<Property, String> {
+        let antecedent = self.sequence()?;
+        let property = if self.accept("|->") || self.accept("|=>") {
+            let overlapping = self.tokens[self.position - 1] == Token::Op("|->");
+            Property::Implication {
+                antecedent,
+                consequent: self.sequence()?,
+                overlapping,
+            }
+        } else {
+            Property::Sequence(antecedent)
+        };
+
+        if self.peek().is_some() {
+            return Err(format!("Unexpected {}", self.describe()));
+        }
+        Ok(property)
+    }
+
+    fn delay(&mut self) -> Result<(u32, u32), String> {
+        if self.accept("[") {
+            let min = self.number()?;
+            self.expect(":")?;
+            let max = self.number()?;
+            self.expect("]")?;
+            if max < min {
+                return Err(format!("Empty delay range [{min}:{max}]"));
+            }
+            Ok((min, max))
+        } else {
+            let delay = self.number()?;
+            Ok((delay, delay))
+        }
+    }
+
+    fn sequence(&mut self) -> Result<Sequence, String> {
+        let mut sequence = if self.accept("##") {
+            // A leading delay starts from an always true item
+            let (min, max) = self.delay()?;
+            Sequence::Concat {
+                first: Box::new(Sequence::Expr(Expr::Const(1))),
+                min,
+                max,
+                second: Box::new(self.item()?),
+            }
+        } else {
+            self.item()?
+        };
+
+        while self.accept("##") {
+            let (min, max) = self.delay()?;
+            sequence = Sequence::Concat {
+                first: Box::new(sequence),
+                min,
+                max,
+                second: Box::new(self.item()?),
+            };
+        }
+
+        Ok(sequence)
+    }
+
+    fn item(&mut self) -> Result<Sequence, String> {
+        if self.peek() == Some(&Token::Ident("eventually".to_owned())) {
+            self.position += 1;
+            self.expect("[")?;
+            let cycles = self.number()?;
+            self.expect("]")?;
+            return Ok(Sequence::Eventually(cycles, self.expr()?));
+        }
+
+        // Parenthesized sequences, falling back to expressions for e.g. `(a || b) && c`
+        if self.peek() == Some(&Token::Op("(")) {
+            let start = self.position;
+            self.position += 1;
+            if let Ok(sequence) = self.sequence() {
+                if !matches!(sequence, Sequence::Expr(_)) && self.accept(")") {
+                    return Ok(sequence);
+                }
+            }
+            self.position = start;
+        }
+
+        let expr = self.expr()?;
+        if self.peek() == Some(&Token::Ident("throughout".to_owned())) {
+            self.position += 1;
+            return Ok(Sequence::Throughout(expr, Box::new(self.item()?)));
+        }
+        Ok(Sequence::Expr(expr))
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary(0)
+    }
+
+    /// Parses binary operators from the given precedence level
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[(&str, BinaryOp)]; 8] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[("|", BinaryOp::BitOr)],
+            &[("^", BinaryOp::BitXor)],
+            &[("&", BinaryOp::BitAnd)],
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            &[
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        'operators: loop {
+            for (token, op) in LEVELS[level] {
+                if self.accept(token) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+                    continue 'operators;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.accept("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if self.accept("~") {
+            Ok(Expr::BitNot(Box::new(self.unary()?)))
+        } else if self.accept("-") {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Const(n)),
+            Some(Token::Op("(")) => {
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if name.starts_with('$') => {
+                self.expect("(")?;
+                let arg = Box::new(self.expr()?);
+                self.expect(")")?;
+                match name.as_str() {
+                    "$rose" => Ok(Expr::Rose(arg)),
+                    "$fell" => Ok(Expr::Fell(arg)),
+                    "$stable" => Ok(Expr::Stable(arg)),
+                    _ => Err(format!("Unknown function {name}")),
+                }
+            }
+            Some(Token::Ident(name)) if name != "throughout" && name != "eventually" => {
+                Ok(Expr::Signal(name))
+            }
+            _ => {
+                self.position -= 1;
+                Err(format!("Unexpected {}", self.describe()))
+            }
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Property, String> {
+    Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    }
+    .property()
+}
+
+/// Parses a boolean expression, e.g. the condition disabling a property
+pub fn parse_expr(text: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let expr = parser.expr()?;
+    if parser.peek().is_some() {
+        return Err(format!("Unexpected {}", parser.describe()));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signal(name: &str) -> Expr {
+        Expr::Signal(name.to_owned())
+    }
+
+    #[test]
+    fn implication() {
+        assert_eq!(
+            parse("$rose(req) && !tb.busy |=> ##[1:3] ack").unwrap(),
+            Property::Implication {
+                antecedent: Sequence::Expr(Expr::Binary(
+                    BinaryOp::And,
+                    Box::new(Expr::Rose(Box::new(signal("req")))),
+                    Box::new(Expr::Not(Box::new(signal("tb.busy"))))
+                )),
+                consequent: Sequence::Concat {
+                    first: Box::new(Sequence::Expr(Expr::Const(1))),
+                    min: 1,
+                    max: 3,
+                    second: Box::new(Sequence::Expr(signal("ack")))
+                },
+                overlapping: false
+            }
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(
+            parse("(a || b) == 4'b0001 throughout (c ##2 d)").unwrap(),
+            Property::Sequence(Sequence::Throughout(
+                Expr::Binary(
+                    BinaryOp::Eq,
+                    Box::new(Expr::Binary(
+                        BinaryOp::Or,
+                        Box::new(signal("a")),
+                        Box::new(signal("b"))
+                    )),
+                    Box::new(Expr::Const(1))
+                ),
+                Box::new(Sequence::Concat {
+                    first: Box::new(Sequence::Expr(signal("c"))),
+                    min: 2,
+                    max: 2,
+                    second: Box::new(Sequence::Expr(signal("d")))
+                })
+            ))
+        );
+        assert_eq!(
+            parse("start |-> eventually [8] done").unwrap(),
+            Property::Implication {
+                antecedent: Sequence::Expr(signal("start")),
+                consequent: Sequence::Eventually(8, signal("done")),
+                overlapping: true
+            }
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("a ##[3:1] b").is_err());
+        assert!(parse("a |-> (b").is_err());
+        assert!(parse("$past(a)").is_err());
+        assert!(parse("a b").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Headless commands, run instead of the application when the first argument is a known command.
+//!
+//! ```text
+//! palusim export <dump.vcd> [--csv | --clock <path> [--edge rising|falling|both] | --json]
+//!                [--start <time>] [--end <time>] [--radix <radix>] [-o <output>] <signal[:radix]>...
+//! palusim check <dump.vcd> [project directory]
+//! ```
+
+use std::{collections::HashMap, fs, str::FromStr};
+
+use crate::{
+    assertions::{self, AssertionOutcome},
+    error::Error,
+    icarus::vcd::VCDFile,
+    project::Project,
+    waves::{
+        export::{export, Column, Edge, ExportFormat, ExportOptions},
+        format::Radix,
+        Source,
+    },
+};
+
+const USAGE: &str = "Usage: palusim export <dump.vcd> [--csv | --clock <path> [--edge rising|falling|both] | --json]
+                      [--start <time>] [--end <time>] [--radix bin|oct|dec|signed|hex] [-o <output>]
+                      <signal[:radix]>...
+       palusim check <dump.vcd> [project directory]";
+
+fn parse_radix(radix: &str) -> Result<Radix, Error> {
+    match radix {
+        "bin" => Ok(Radix::Binary),
+        "oct" => Ok(Radix::Octal),
+        "dec" => Ok(Radix::Decimal),
+        "signed" => Ok(Radix::Signed),
+        "hex" => Ok(Radix::Hexadecimal),
+        _ => Err(Error::Other(format!("Unknown radix {radix}"))),
+    }
+}
+
+fn parse_time(time: &str) -> Result<u32, Error> {
+    time.parse()
+        .map_err(|_| Error::Other(format!("Invalid time {time}")))
+}
+
+fn export_command(args: &[String]) -> Result<(), Error> {
+    let mut args = args.iter();
+    let mut dump = None;
+    let mut output = None;
+    let mut radix = Radix::default();
+    let mut signals = vec![];
+    let mut clock = None;
+    let mut edge = Edge::default();
+    let mut json = false;
+    let mut csv = false;
+    let mut start = None;
+    let mut end = None;
+
+    let value = |args: &mut std::slice::Iter<String>, option: &str| {
+        args.next()
+            .cloned()
+            .ok_or_else(|| Error::Other(format!("Missing value for {option}")))
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => csv = true,
+            "--json" => json = true,
+            "--clock" => clock = Some(value(&mut args, arg)?),
+            "--edge" => {
+                edge = match value(&mut args, arg)?.as_str() {
+                    "rising" => Edge::Rising,
+                    "falling" => Edge::Falling,
+                    "both" => Edge::Both,
+                    e => return Err(Error::Other(format!("Unknown edge {e}"))),
+                }
+            }
+            "--start" => start = Some(parse_time(&value(&mut args, arg)?)?),
+            "--end" => end = Some(parse_time(&value(&mut args, arg)?)?),
+            "--radix" => radix = parse_radix(&value(&mut args, arg)?)?,
+            "-o" | "--output" => output = Some(value(&mut args, arg)?),
+            _ if arg.starts_with('-') => return Err(Error::Other(format!("Unknown option {arg}"))),
+            _ if dump.is_none() => dump = Some(arg.clone()),
+            _ => signals.push(arg.clone()),
+        }
+    }
+
+    let dump = dump.ok_or_else(|| Error::Other("Missing dump file".to_owned()))?;
+    if [csv, json, clock.is_some()].iter().filter(|f| **f).count() > 1 {
+        return Err(Error::Other(
+            "Only one of --csv, --clock and --json can be given".to_owned(),
+        ));
+    }
+    if signals.is_empty() {
+        return Err(Error::Other("No signal to export".to_owned()));
+    }
+
+    let columns = signals
+        .into_iter()
+        .map(|signal| {
+            Ok(match signal.split_once(':') {
+                Some((path, r)) => Column {
+                    source: Source::Signal {
+                        path: path.to_owned(),
+                    },
+                    radix: parse_radix(r)?,
+                },
+                None => Column {
+                    source: Source::Signal { path: signal },
+                    radix,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let format = match (json, clock) {
+        (true, _) => ExportFormat::Json,
+        (false, Some(clock)) => ExportFormat::Clocked { clock, edge },
+        (false, None) => ExportFormat::Changes,
+    };
+
+    let vcd = read_dump(&dump)?;
+    let content = export(
+        &vcd,
+        &ExportOptions {
+            columns,
+            start,
+            end,
+            format,
+        },
+        &HashMap::new(),
+    )?;
+
+    match output {
+        Some(output) => fs::write(output, content)?,
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+fn read_dump(path: &str) -> Result<VCDFile, Error> {
+    VCDFile::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| Error::Other(format!("Could not parse {path}: {e}")))
+}
+
+/// Checks the assertions of the project on a dump, failing if any of them does not hold
+fn check_command(args: &[String]) -> Result<(), Error> {
+    let (dump, project) = match args {
+        [dump] => (dump, Project::from_current_dir()?),
+        [dump, directory] => (dump, Project::from_dir(directory.into())?),
+        _ => return Err(Error::Other("Expected a dump file".to_owned())),
+    };
+    let vcd = read_dump(dump)?;
+
+    let mut failed = 0;
+    for assertion in &project.assertions {
+        match assertions::report(&vcd, assertion).outcome {
+            AssertionOutcome::Passed { cycles } => {
+                println!("PASS {} ({cycles} cycles)", assertion.name)
+            }
+            AssertionOutcome::Failed { failures, .. } => {
+                failed += 1;
+                println!("FAIL {}", assertion.name);
+                for failure in failures {
+                    let values = failure
+                        .values
+                        .iter()
+                        .map(|(path, value)| format!("{path}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    println!(
+                        "  at {} (started at {}): {values}",
+                        failure.time, failure.start
+                    );
+                }
+            }
+            AssertionOutcome::Error { message } => {
+                failed += 1;
+                println!("ERROR {}: {message}", assertion.name);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Other(format!(
+            "{failed} of {} assertions failed",
+            project.assertions.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Runs the headless command given in `args` (without the program name), returning its exit code,
+/// or `None` if the application should be started instead
+pub fn run(args: &[String]) -> Option<i32> {
+    let result = match args.first().map(String::as_str) {
+        Some("export") => export_command(&args[1..]),
+        Some("check") => check_command(&args[1..]),
+        Some("help" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("{e:?}\n{USAGE}");
+            Some(1)
+        }
+    }
+}
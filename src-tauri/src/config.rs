@@ -1 +1,5 @@
 pub const APP_NAME: &str = "PaluSim";
+/// Name of the configuration file, at the root of a project
+pub const PROJECT_FILE: &str = "palusim.json";
+/// Name of the file storing the waveform viewer session, at the root of a project
+pub const SESSION_FILE: &str = "palusim.session.json";
@@ -0,0 +1,157 @@
+//! AXI4-Lite decoder, pairing address, data and response handshakes into read and write transactions.
+
+use std::collections::VecDeque;
+
+use super::bus::{hex, BusDecoder, Sample, Transaction};
+
+/// Handshake state of an AXI channel
+#[derive(Debug, Default)]
+struct Channel {
+    /// Edge at which the current beat was first offered
+    pending_since: Option<u32>,
+}
+
+impl Channel {
+    /// Returns the time at which the beat was offered if the handshake completes at this edge
+    fn handshake(&mut self, time: u32, values: &Sample, channel: &str) -> Option<u32> {
+        if !values.high(&format!("{channel}valid")) {
+            self.pending_since = None;
+            return None;
+        }
+
+        let start = *self.pending_since.get_or_insert(time);
+        if values.high(&format!("{channel}ready")) {
+            self.pending_since = None;
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AxiLiteDecoder {
+    aw: Channel,
+    w: Channel,
+    b: Channel,
+    ar: Channel,
+    r: Channel,
+    /// Accepted write addresses waiting for their response, with the time they were offered
+    write_addresses: VecDeque<(u32, Option<u64>)>,
+    write_data: VecDeque<(u32, Option<u64>)>,
+    read_addresses: VecDeque<(u32, Option<u64>)>,
+}
+
+fn response(resp: Option<u64>) -> &'static str {
+    match resp {
+        Some(0) | None => "",
+        Some(1) => " (EXOKAY)",
+        Some(2) => " (SLVERR)",
+        _ => " (DECERR)",
+    }
+}
+
+impl BusDecoder for AxiLiteDecoder {
+    fn required_signals(&self) -> &'static [&'static str] {
+        &[
+            "awvalid", "awready", "wvalid", "wready", "bvalid", "bready", "arvalid", "arready",
+            "rvalid", "rready",
+        ]
+    }
+
+    fn optional_signals(&self) -> &'static [&'static str] {
+        &["awaddr", "wdata", "bresp", "araddr", "rdata", "rresp"]
+    }
+
+    fn sample(&mut self, time: u32, values: &Sample, transactions: &mut Vec<Transaction>) {
+        if let Some(start) = self.aw.handshake(time, values, "aw") {
+            self.write_addresses
+                .push_back((start, values.get("awaddr")));
+        }
+        if let Some(start) = self.w.handshake(time, values, "w") {
+            self.write_data.push_back((start, values.get("wdata")));
+        }
+        if let Some(start) = self.ar.handshake(time, values, "ar") {
+            self.read_addresses.push_back((start, values.get("araddr")));
+        }
+
+        if self.b.handshake(time, values, "b").is_some() {
+            if let (Some(address), Some(data)) = (
+                self.write_addresses.pop_front(),
+                self.write_data.pop_front(),
+            ) {
+                transactions.push(Transaction {
+                    start: address.0.min(data.0),
+                    end: time,
+                    label: format!(
+                        "WRITE {} = {}{}",
+                        hex(address.1),
+                        hex(data.1),
+                        response(values.get("bresp"))
+                    ),
+                });
+            }
+        }
+        if self.r.handshake(time, values, "r").is_some() {
+            if let Some(address) = self.read_addresses.pop_front() {
+                transactions.push(Transaction {
+                    start: address.0,
+                    end: time,
+                    label: format!(
+                        "READ {} = {}{}",
+                        hex(address.1),
+                        hex(values.get("rdata")),
+                        response(values.get("rresp"))
+                    ),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::bus::run;
+
+    #[test]
+    fn write_and_read() {
+        let res = run(
+            &mut AxiLiteDecoder::default(),
+            &[
+                ("awvalid", &[(0, 0), (12, 1), (22, 0)]),
+                ("awready", &[(0, 1)]),
+                ("awaddr", &[(0, 0x4)]),
+                ("wvalid", &[(0, 0), (12, 1), (32, 0)]),
+                ("wready", &[(0, 0), (22, 1)]),
+                ("wdata", &[(0, 0x1234)]),
+                ("bvalid", &[(0, 0), (42, 1), (52, 0)]),
+                ("bready", &[(0, 1)]),
+                ("arvalid", &[(0, 0), (52, 1), (62, 0)]),
+                ("arready", &[(0, 1)]),
+                ("araddr", &[(0, 0x8)]),
+                ("rvalid", &[(0, 0), (62, 1), (72, 0)]),
+                ("rready", &[(0, 1)]),
+                ("rdata", &[(0, 0x42)]),
+                ("rresp", &[(0, 2)]),
+            ],
+            8,
+        );
+
+        assert_eq!(
+            res,
+            vec![
+                Transaction {
+                    start: 20,
+                    end: 50,
+                    label: "WRITE 0x4 = 0x1234".to_owned()
+                },
+                Transaction {
+                    start: 60,
+                    end: 70,
+                    label: "READ 0x8 = 0x42 (SLVERR)".to_owned()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Synchronous bus decoders, fed with the values of their signals on each active clock edge.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    axi_lite::AxiLiteDecoder, valid_ready::ValidReadyDecoder, wishbone::WishboneDecoder,
+    Annotation, AnnotationKind, Signal,
+};
+use crate::{error::Error, icarus::vcd::VCDFile};
+
+/// Decoder of a synchronous bus.
+///
+/// To add a decoder, implement this trait and register it in [`bus_decoder`].
+pub trait BusDecoder {
+    /// Roles of the signals which must be bound to decode the bus (e.g. `valid`, `ready`)
+    fn required_signals(&self) -> &'static [&'static str];
+
+    /// Roles of the signals which may be bound. Unbound signals are sampled as unknown.
+    fn optional_signals(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Handles the values sampled on the active clock edge at `time`
+    fn sample(&mut self, time: u32, values: &Sample, transactions: &mut Vec<Transaction>);
+}
+
+/// Values of the bound signals, sampled on a clock edge
+pub struct Sample<'a> {
+    values: HashMap<&'a str, Option<u64>>,
+}
+
+impl Sample<'_> {
+    /// Value of the signal bound to `role`, `None` if unbound or unknown
+    pub fn get(&self, role: &str) -> Option<u64> {
+        self.values.get(role).copied().flatten()
+    }
+
+    /// Whether the signal bound to `role` is known and non-zero
+    pub fn high(&self, role: &str) -> bool {
+        self.get(role).is_some_and(|v| v != 0)
+    }
+}
+
+/// Span of time covered by a bus transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub start: u32,
+    pub end: u32,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusConfig {
+    /// Name of the decoder, e.g. `validReady`, `wishbone` or `axiLite`
+    pub decoder: String,
+    pub clock: String,
+    /// Samples on falling edges of the clock instead of rising ones
+    #[serde(default)]
+    pub falling_edge: bool,
+    /// Mapping of the decoder's signal roles to signal paths
+    pub signals: HashMap<String, String>,
+}
+
+/// Formats a sampled value for a transaction label
+pub fn hex(value: Option<u64>) -> String {
+    value.map_or("?".to_owned(), |v| format!("0x{v:x}"))
+}
+
+/// Instantiates a bus decoder from its name
+pub fn bus_decoder(name: &str) -> Option<Box<dyn BusDecoder>> {
+    match name {
+        "validReady" => Some(Box::<ValidReadyDecoder>::default()),
+        "wishbone" => Some(Box::<WishboneDecoder>::default()),
+        "axiLite" => Some(Box::<AxiLiteDecoder>::default()),
+        _ => None,
+    }
+}
+
+/// Decodes the transactions of a bus, annotating them in the row `row`
+pub fn decode(vcd: &VCDFile, config: &BusConfig, row: &str) -> Result<Vec<Annotation>, Error> {
+    let mut decoder = bus_decoder(&config.decoder)
+        .ok_or_else(|| Error::Other(format!("Unknown bus decoder {}", config.decoder)))?;
+
+    if let Some(missing) = decoder
+        .required_signals()
+        .iter()
+        .find(|role| !config.signals.contains_key(**role))
+    {
+        return Err(Error::Other(format!(
+            "Missing signal binding {missing} for decoder {}",
+            config.decoder
+        )));
+    }
+
+    let mut signals = vec![];
+    for role in decoder
+        .required_signals()
+        .iter()
+        .chain(decoder.optional_signals())
+    {
+        if let Some(path) = config.signals.get(*role) {
+            signals.push((*role, Signal::from_vcd(vcd, path)?));
+        }
+    }
+
+    let clock = Signal::from_vcd(vcd, &config.clock)?;
+    Ok(decode_signals(decoder.as_mut(), &clock, &signals, config)
+        .into_iter()
+        .map(|t| {
+            Annotation::new(
+                row,
+                t.start,
+                t.end,
+                AnnotationKind::Transaction { label: t.label },
+            )
+        })
+        .collect())
+}
+
+fn decode_signals(
+    decoder: &mut dyn BusDecoder,
+    clock: &Signal,
+    signals: &[(&str, Signal)],
+    config: &BusConfig,
+) -> Vec<Transaction> {
+    let mut transactions = vec![];
+
+    for (time, _) in clock
+        .edges()
+        .filter(|(_, level)| *level != config.falling_edge)
+    {
+        let sample = Sample {
+            values: signals
+                .iter()
+                .map(|(role, signal)| (*role, signal.value_before(time)))
+                .collect(),
+        };
+        decoder.sample(time, &sample, &mut transactions);
+    }
+
+    transactions
+}
+
+#[cfg(test)]
+pub(crate) fn run(
+    decoder: &mut dyn BusDecoder,
+    signals: &[(&str, &[(u32, u64)])],
+    cycles: u32,
+) -> Vec<Transaction> {
+    // Clock with a period of 10 ticks, rising at 10, 20, ...
+    let mut clock = vec![(0, Some(0))];
+    for i in 1..=cycles {
+        clock.push((i * 10, Some(1)));
+        clock.push((i * 10 + 5, Some(0)));
+    }
+
+    let signals = signals
+        .iter()
+        .map(|(role, changes)| {
+            (
+                *role,
+                Signal::new(changes.iter().map(|(t, v)| (*t, Some(*v))).collect()),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    decode_signals(
+        decoder,
+        &Signal::new(clock),
+        &signals,
+        &BusConfig {
+            decoder: String::new(),
+            clock: String::new(),
+            falling_edge: false,
+            signals: HashMap::new(),
+        },
+    )
+}
@@ -0,0 +1,182 @@
+//! I2C decoder, supporting 7-bit addressing and repeated starts.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Annotation, AnnotationKind, Signal};
+use crate::{error::Error, icarus::vcd::VCDFile};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct I2cConfig {
+    pub scl: String,
+    pub sda: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Address,
+    Data,
+}
+
+pub fn decode(vcd: &VCDFile, config: &I2cConfig) -> Result<Vec<Annotation>, Error> {
+    Ok(decode_bus(
+        &Signal::from_vcd(vcd, &config.scl)?,
+        &Signal::from_vcd(vcd, &config.sda)?,
+        &config.sda,
+    ))
+}
+
+fn decode_bus(scl: &Signal, sda: &Signal, row: &str) -> Vec<Annotation> {
+    let mut times = scl.times().chain(sda.times()).collect::<Vec<_>>();
+    times.sort();
+    times.dedup();
+
+    let mut annotations = vec![];
+    let mut phase = Phase::Idle;
+    let mut bits = vec![];
+    let mut byte_start = 0;
+    let mut byte_end = 0;
+
+    for time in times {
+        let scl_before = scl.level_before(time);
+        let scl_now = scl.level_at(time);
+        let sda_before = sda.level_before(time);
+        let sda_now = sda.level_at(time);
+
+        let scl_high = scl_before == Some(true) && scl_now == Some(true);
+        if scl_high && sda_before == Some(true) && sda_now == Some(false) {
+            // (Repeated) start condition
+            annotations.push(Annotation::new(row, time, time, AnnotationKind::Start));
+            phase = Phase::Address;
+            bits.clear();
+        } else if scl_high && sda_before == Some(false) && sda_now == Some(true) {
+            // The clock pulse preceding a stop condition is sampled as the first bit of a byte
+            bits.clear();
+            annotations.push(Annotation::new(row, time, time, AnnotationKind::Stop));
+            phase = Phase::Idle;
+        } else if phase != Phase::Idle && scl_before == Some(false) && scl_now == Some(true) {
+            // SDA only changes while SCL is low, so its value before the edge is the sampled bit
+            if bits.is_empty() {
+                byte_start = time;
+            }
+            bits.push(sda_before);
+
+            if bits.len() == 8 {
+                byte_end = time;
+            } else if bits.len() == 9 {
+                annotations.push(byte_annotation(
+                    row,
+                    byte_start,
+                    byte_end,
+                    &bits[..8],
+                    phase,
+                ));
+                annotations.push(Annotation::new(
+                    row,
+                    byte_end,
+                    time,
+                    match bits[8] {
+                        Some(false) => AnnotationKind::Ack,
+                        _ => AnnotationKind::Nak,
+                    },
+                ));
+                phase = Phase::Data;
+                bits.clear();
+            }
+        }
+    }
+
+    annotations
+}
+
+fn byte_annotation(
+    row: &str,
+    start: u32,
+    end: u32,
+    bits: &[Option<bool>],
+    phase: Phase,
+) -> Annotation {
+    let mut byte = 0u8;
+    for bit in bits {
+        let Some(bit) = bit else {
+            return Annotation::error(row, start, end, "Undefined data bit");
+        };
+        byte = (byte << 1) | *bit as u8;
+    }
+
+    let kind = if phase == Phase::Address {
+        AnnotationKind::Address {
+            address: (byte >> 1) as u16,
+            read: byte & 1 == 1,
+        }
+    } else {
+        AnnotationKind::Data { value: byte as u64 }
+    };
+    Annotation::new(row, start, end, kind)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::signal;
+
+    /// Builds a transaction of several 9-bit frames, with a clock period of 10 ticks
+    fn transaction(frames: &[u16]) -> (Signal, Signal) {
+        let mut scl = vec![(0, true)];
+        let mut sda = vec![(0, true), (5, false)];
+
+        let mut time = 10;
+        for frame in frames {
+            for i in (0..9).rev() {
+                scl.push((time, false));
+                sda.push((time + 2, (frame >> i) & 1 == 1));
+                scl.push((time + 5, true));
+                time += 10;
+            }
+        }
+        scl.push((time, false));
+        sda.push((time + 2, false));
+        scl.push((time + 5, true));
+        sda.push((time + 8, true));
+
+        (signal(&scl), signal(&sda))
+    }
+
+    #[test]
+    fn write_transaction() {
+        // Address 0x50, write, ACK, then data 0xA5, NAK
+        let (scl, sda) = transaction(&[0x50 << 2, 0xA5 << 1 | 1]);
+        let res = decode_bus(&scl, &sda, "sda");
+
+        let kinds = res.into_iter().map(|a| a.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                AnnotationKind::Start,
+                AnnotationKind::Address {
+                    address: 0x50,
+                    read: false
+                },
+                AnnotationKind::Ack,
+                AnnotationKind::Data { value: 0xA5 },
+                AnnotationKind::Nak,
+                AnnotationKind::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_transaction() {
+        let (scl, sda) = transaction(&[(0x12 << 1 | 1) << 1]);
+        let res = decode_bus(&scl, &sda, "sda");
+
+        assert_eq!(
+            res[1].kind,
+            AnnotationKind::Address {
+                address: 0x12,
+                read: true
+            }
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Protocol decoders, annotating waveform signals with the transactions they carry.
+
+mod axi_lite;
+mod bus;
+mod i2c;
+mod spi;
+mod uart;
+mod valid_ready;
+mod wishbone;
+
+use serde::{Deserialize, Serialize};
+
+pub use bus::{BusConfig, BusDecoder, Sample, Transaction};
+pub use i2c::I2cConfig;
+pub use spi::{BitOrder, SpiConfig};
+pub use uart::{Parity, UartConfig};
+
+use std::collections::HashMap;
+
+use crate::{error::Error, icarus::vcd::VCDFile, state::AppState, waves::dump};
+
+/// Configuration of a decoder, tagged with the decoded protocol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "protocol")]
+pub enum DecoderConfig {
+    Uart(UartConfig),
+    Spi(SpiConfig),
+    I2c(I2cConfig),
+    Bus(BusConfig),
+}
+
+/// Decoder declared in the project file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecoderInstance {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: DecoderConfig,
+}
+
+/// Span of time annotated by a decoder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    /// Row in which the annotation is displayed, i.e. the path of the decoded signal, or the name of
+    /// the decoder for the bus decoders
+    pub row: String,
+    pub start: u32,
+    pub end: u32,
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum AnnotationKind {
+    Start,
+    Stop,
+    Address { address: u16, read: bool },
+    Data { value: u64 },
+    Ack,
+    Nak,
+    Error { message: String },
+    Transaction { label: String },
+}
+
+impl Annotation {
+    pub fn new(row: &str, start: u32, end: u32, kind: AnnotationKind) -> Self {
+        Self {
+            row: row.to_owned(),
+            start,
+            end,
+            kind,
+        }
+    }
+
+    pub fn error(row: &str, start: u32, end: u32, message: &str) -> Self {
+        Self::new(
+            row,
+            start,
+            end,
+            AnnotationKind::Error {
+                message: message.to_owned(),
+            },
+        )
+    }
+}
+
+/// Sorted value changes of a signal. Values containing unknown bits (`x`, `z`) are stored as `None`.
+#[derive(Debug, Default, Clone)]
+pub struct Signal {
+    changes: Vec<(u32, Option<u64>)>,
+}
+
+impl Signal {
+    pub fn new(mut changes: Vec<(u32, Option<u64>)>) -> Self {
+        changes.sort_by_key(|c| c.0);
+        Self { changes }
+    }
+
+    pub fn from_vcd(vcd: &VCDFile, path: &str) -> Result<Self, Error> {
+        let variable = vcd
+            .find_variable(path)
+            .ok_or_else(|| Error::Other(format!("Unknown signal {path}")))?;
+
+        Ok(Self::new(
+            vcd.changes(variable.identifier)
+                .into_iter()
+                .map(|(time, value)| (time, parse_value(value)))
+                .collect(),
+        ))
+    }
+
+    /// Value of the signal at `time`, including a change happening at `time`
+    pub fn value_at(&self, time: u32) -> Option<u64> {
+        let idx = self.changes.partition_point(|c| c.0 <= time);
+        idx.checked_sub(1).and_then(|i| self.changes[i].1)
+    }
+
+    /// Value of the signal just before `time`, which is what a flip-flop clocked at `time` samples
+    pub fn value_before(&self, time: u32) -> Option<u64> {
+        let idx = self.changes.partition_point(|c| c.0 < time);
+        idx.checked_sub(1).and_then(|i| self.changes[i].1)
+    }
+
+    /// Level of the least significant bit at `time`
+    pub fn level_at(&self, time: u32) -> Option<bool> {
+        self.value_at(time).map(|v| v & 1 == 1)
+    }
+
+    /// Level of the least significant bit just before `time`
+    pub fn level_before(&self, time: u32) -> Option<bool> {
+        self.value_before(time).map(|v| v & 1 == 1)
+    }
+
+    /// Transitions of the least significant bit between two known levels, as `(time, new level)`
+    pub fn edges(&self) -> impl Iterator<Item = (u32, bool)> + '_ {
+        self.changes
+            .windows(2)
+            .filter_map(|w| match (w[0].1, w[1].1) {
+                (Some(prev), Some(next)) if prev & 1 != next & 1 => Some((w[1].0, next & 1 == 1)),
+                _ => None,
+            })
+    }
+
+    /// Times at which the signal changes
+    pub fn times(&self) -> impl Iterator<Item = u32> + '_ {
+        self.changes.iter().map(|c| c.0)
+    }
+}
+
+/// Parses a binary value from the dump (e.g. `1`, `b0110`), keeping the 64 least significant bits
+fn parse_value(value: &str) -> Option<u64> {
+    let digits = value.strip_prefix(['b', 'B']).unwrap_or(value);
+    if digits.is_empty() {
+        return None;
+    }
+
+    digits.chars().try_fold(0u64, |acc, c| match c {
+        '0' => Some(acc << 1),
+        '1' => Some(acc << 1 | 1),
+        _ => None,
+    })
+}
+
+/// Decodes a protocol on `vcd`. The transactions of a bus are annotated in the row `row`, the kind
+/// of bus by default.
+pub fn decode_vcd(
+    vcd: &VCDFile,
+    config: &DecoderConfig,
+    row: Option<&str>,
+) -> Result<Vec<Annotation>, Error> {
+    match config {
+        DecoderConfig::Uart(config) => uart::decode(vcd, config),
+        DecoderConfig::Spi(config) => spi::decode(vcd, config),
+        DecoderConfig::I2c(config) => i2c::decode(vcd, config),
+        DecoderConfig::Bus(config) => bus::decode(vcd, config, row.unwrap_or(&config.decoder)),
+    }
+}
+
+/// Decodes a protocol on the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn decode(
+    file: usize,
+    decoder: DecoderConfig,
+    state: AppState<'_>,
+) -> Result<Vec<Annotation>, Error> {
+    decode_vcd(dump(&state.lock().unwrap(), file)?, &decoder, None)
+}
+
+/// Runs the decoders declared in the project on the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn decode_project(
+    file: usize,
+    state: AppState<'_>,
+) -> Result<HashMap<String, Vec<Annotation>>, Error> {
+    let state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?;
+    let vcd = dump(&state, file)?;
+
+    project
+        .decoders
+        .iter()
+        .map(|d| Ok((d.name.clone(), decode_vcd(vcd, &d.config, Some(&d.name))?)))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn signal(levels: &[(u32, bool)]) -> Signal {
+    Signal::new(levels.iter().map(|(t, l)| (*t, Some(*l as u64))).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decoder_instance() {
+        let res = serde_json::from_str::<DecoderInstance>(
+            r#"{
+                "name": "stream",
+                "protocol": "bus",
+                "decoder": "validReady",
+                "clock": "tb.clk",
+                "signals": { "valid": "tb.valid", "ready": "tb.ready" }
+            }"#,
+        );
+
+        assert!(res.is_ok(), "error: {:?}", res);
+        let res = res.unwrap();
+        assert_eq!(res.name, "stream");
+        assert!(
+            matches!(res.config, DecoderConfig::Bus(BusConfig { ref decoder, .. }) if decoder == "validReady")
+        );
+    }
+
+    #[test]
+    fn signal_levels() {
+        let s = signal(&[(0, false), (10, true), (20, false)]);
+
+        assert_eq!(s.level_at(10), Some(true));
+        assert_eq!(s.level_before(10), Some(false));
+        assert_eq!(s.level_at(25), Some(false));
+        assert_eq!(s.edges().collect::<Vec<_>>(), vec![(10, true), (20, false)]);
+    }
+
+    #[test]
+    fn value_parsing() {
+        assert_eq!(parse_value("1"), Some(1));
+        assert_eq!(parse_value("b0110"), Some(6));
+        assert_eq!(parse_value("b1x"), None);
+        assert_eq!(parse_value("r1.5"), None);
+    }
+}
@@ -0,0 +1,297 @@
+//! SPI decoder, supporting the four clock modes and both bit orders.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Annotation, AnnotationKind, Signal};
+use crate::{error::Error, icarus::vcd::VCDFile};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpiConfig {
+    pub clock: String,
+    #[serde(default)]
+    pub mosi: Option<String>,
+    #[serde(default)]
+    pub miso: Option<String>,
+    /// Active-low chip select. Without it, every sampling edge is decoded.
+    #[serde(default)]
+    pub chip_select: Option<String>,
+    /// Clock polarity, `true` if the clock idles high
+    #[serde(default)]
+    pub cpol: bool,
+    /// Clock phase, `true` if data is sampled on the trailing edge
+    #[serde(default)]
+    pub cpha: bool,
+    #[serde(default)]
+    pub bit_order: BitOrder,
+    #[serde(default = "default_word_size")]
+    pub word_size: u8,
+}
+
+fn default_word_size() -> u8 {
+    8
+}
+
+/// Decoded data line, with the bits of the current word
+struct Line<'a> {
+    row: &'a str,
+    signal: Signal,
+    bits: Vec<Option<bool>>,
+}
+
+pub fn decode(vcd: &VCDFile, config: &SpiConfig) -> Result<Vec<Annotation>, Error> {
+    // Words are decoded into a u64
+    if !(1..=64).contains(&config.word_size) {
+        return Err(Error::Other(format!(
+            "Invalid word size {}, must be between 1 and 64",
+            config.word_size
+        )));
+    }
+    let clock = Signal::from_vcd(vcd, &config.clock)?;
+    let chip_select = config
+        .chip_select
+        .as_ref()
+        .map(|cs| Ok::<_, Error>((cs.as_str(), Signal::from_vcd(vcd, cs)?)))
+        .transpose()?;
+
+    let mut lines = vec![];
+    for row in config.mosi.iter().chain(config.miso.iter()) {
+        lines.push(Line {
+            row,
+            signal: Signal::from_vcd(vcd, row)?,
+            bits: vec![],
+        });
+    }
+
+    Ok(decode_lines(
+        &clock,
+        chip_select.as_ref(),
+        &mut lines,
+        config,
+    ))
+}
+
+fn decode_lines(
+    clock: &Signal,
+    chip_select: Option<&(&str, Signal)>,
+    lines: &mut [Line],
+    config: &SpiConfig,
+) -> Vec<Annotation> {
+    enum Event {
+        Select,
+        Deselect,
+        Sample,
+    }
+
+    // Data is sampled on the leading edge when CPHA is 0, which is rising when CPOL is 0
+    let sampling_level = config.cpol == config.cpha;
+
+    let mut events = vec![];
+    if let Some((_, cs)) = chip_select {
+        events.extend(cs.edges().map(|(t, level)| {
+            (
+                t,
+                if level {
+                    Event::Deselect
+                } else {
+                    Event::Select
+                },
+            )
+        }));
+    }
+    events.extend(
+        clock
+            .edges()
+            .filter(|(_, level)| *level == sampling_level)
+            .map(|(t, _)| (t, Event::Sample)),
+    );
+    events.sort_by_key(|e| e.0);
+
+    let mut annotations = vec![];
+    let mut word_start = 0;
+    for (time, event) in events {
+        match event {
+            Event::Select => {
+                annotations.push(Annotation::new(
+                    chip_select.unwrap().0,
+                    time,
+                    time,
+                    AnnotationKind::Start,
+                ));
+                lines.iter_mut().for_each(|l| l.bits.clear());
+            }
+            Event::Deselect => {
+                for line in lines.iter_mut() {
+                    if !line.bits.is_empty() {
+                        annotations.push(Annotation::error(
+                            line.row,
+                            word_start,
+                            time,
+                            "Incomplete word",
+                        ));
+                        line.bits.clear();
+                    }
+                }
+                annotations.push(Annotation::new(
+                    chip_select.unwrap().0,
+                    time,
+                    time,
+                    AnnotationKind::Stop,
+                ));
+            }
+            Event::Sample => {
+                if chip_select.is_some_and(|(_, cs)| cs.level_before(time) != Some(false)) {
+                    continue;
+                }
+
+                for line in lines.iter_mut() {
+                    if line.bits.is_empty() {
+                        word_start = time;
+                    }
+                    line.bits.push(line.signal.level_before(time));
+
+                    if line.bits.len() == config.word_size as usize {
+                        annotations.push(word_annotation(
+                            line.row,
+                            word_start,
+                            time,
+                            &line.bits,
+                            config.bit_order,
+                        ));
+                        line.bits.clear();
+                    }
+                }
+            }
+        }
+    }
+
+    annotations
+}
+
+fn word_annotation(
+    row: &str,
+    start: u32,
+    end: u32,
+    bits: &[Option<bool>],
+    order: BitOrder,
+) -> Annotation {
+    let mut value = 0u64;
+    for (i, bit) in bits.iter().enumerate() {
+        let Some(bit) = bit else {
+            return Annotation::error(row, start, end, "Undefined data bit");
+        };
+        let shift = match order {
+            BitOrder::MsbFirst => bits.len() - 1 - i,
+            BitOrder::LsbFirst => i,
+        };
+        value |= (*bit as u64) << shift;
+    }
+    Annotation::new(row, start, end, AnnotationKind::Data { value })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::signal;
+
+    fn config(cpol: bool, cpha: bool, bit_order: BitOrder) -> SpiConfig {
+        SpiConfig {
+            clock: "sclk".to_owned(),
+            mosi: Some("mosi".to_owned()),
+            miso: None,
+            chip_select: Some("cs".to_owned()),
+            cpol,
+            cpha,
+            bit_order,
+            word_size: 4,
+        }
+    }
+
+    /// Clock with a period of 10 ticks, starting idle at `idle` and toggling from 10 to 90
+    fn clock(idle: bool) -> Signal {
+        let mut levels = vec![(0, idle)];
+        for i in 0..8 {
+            levels.push((10 + 10 * i, (i % 2 == 0) != idle));
+        }
+        signal(&levels)
+    }
+
+    /// MOSI shifting 1, 0, 1, 1, changing at 5, 25, 45, 65
+    fn mosi() -> Signal {
+        signal(&[(0, false), (5, true), (25, false), (45, true)])
+    }
+
+    fn decode_mode(cpol: bool, cpha: bool, bit_order: BitOrder) -> Vec<Annotation> {
+        let cs = ("cs", signal(&[(0, true), (2, false), (95, true)]));
+        let mut lines = [Line {
+            row: "mosi",
+            signal: mosi(),
+            bits: vec![],
+        }];
+        decode_lines(
+            &clock(cpol),
+            Some(&cs),
+            &mut lines,
+            &config(cpol, cpha, bit_order),
+        )
+    }
+
+    #[test]
+    fn mode_0() {
+        let res = decode_mode(false, false, BitOrder::MsbFirst);
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].kind, AnnotationKind::Start);
+        assert_eq!(res[1].kind, AnnotationKind::Data { value: 0b1011 });
+        assert_eq!((res[1].start, res[1].end), (10, 70));
+        assert_eq!(res[2].kind, AnnotationKind::Stop);
+    }
+
+    #[test]
+    fn mode_3_lsb_first() {
+        let res = decode_mode(true, true, BitOrder::LsbFirst);
+
+        assert_eq!(res[1].kind, AnnotationKind::Data { value: 0b1101 });
+    }
+
+    #[test]
+    fn incomplete_word() {
+        let res = decode_mode(false, true, BitOrder::MsbFirst);
+
+        // Samples on falling edges (20, 40, 60, 80), then the word is complete
+        assert_eq!(res[1].kind, AnnotationKind::Data { value: 0b1011 });
+
+        let cs = ("cs", signal(&[(0, true), (2, false), (45, true)]));
+        let mut lines = [Line {
+            row: "mosi",
+            signal: mosi(),
+            bits: vec![],
+        }];
+        let res = decode_lines(
+            &clock(false),
+            Some(&cs),
+            &mut lines,
+            &config(false, false, BitOrder::MsbFirst),
+        );
+        assert!(matches!(res[1].kind, AnnotationKind::Error { .. }));
+    }
+
+    #[test]
+    fn invalid_word_size() {
+        for word_size in [0, 65] {
+            let config = SpiConfig {
+                word_size,
+                ..config(false, false, BitOrder::MsbFirst)
+            };
+            assert!(decode(&VCDFile::default(), &config).is_err());
+        }
+    }
+}
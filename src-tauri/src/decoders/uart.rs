@@ -0,0 +1,223 @@
+//! UART decoder, sampling each bit in the middle of its period.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Annotation, AnnotationKind, Signal};
+use crate::{error::Error, icarus::vcd::VCDFile};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UartConfig {
+    /// Path of the decoded line, idle high
+    pub signal: String,
+    pub baud_rate: u32,
+    #[serde(default = "default_data_bits")]
+    pub data_bits: u8,
+    #[serde(default)]
+    pub parity: Parity,
+    #[serde(default = "default_stop_bits")]
+    pub stop_bits: u8,
+}
+
+fn default_data_bits() -> u8 {
+    8
+}
+
+fn default_stop_bits() -> u8 {
+    1
+}
+
+pub fn decode(vcd: &VCDFile, config: &UartConfig) -> Result<Vec<Annotation>, Error> {
+    if config.baud_rate == 0 {
+        return Err(Error::Other("Baud rate cannot be 0".to_owned()));
+    }
+    // Data bits are decoded into a u64
+    if !(1..=64).contains(&config.data_bits) {
+        return Err(Error::Other(format!(
+            "Invalid number of data bits {}, must be between 1 and 64",
+            config.data_bits
+        )));
+    }
+
+    let period = 1.0 / config.baud_rate as f64 / vcd.timescale_seconds();
+    Ok(decode_line(
+        &Signal::from_vcd(vcd, &config.signal)?,
+        period,
+        config,
+    ))
+}
+
+/// Decodes the frames of `line`, `period` being the duration of a bit in ticks
+fn decode_line(line: &Signal, period: f64, config: &UartConfig) -> Vec<Annotation> {
+    let row = config.signal.as_str();
+    let mut annotations = vec![];
+    let mut resume = 0;
+
+    for (start, level) in line.edges() {
+        if level || start < resume {
+            continue;
+        }
+
+        let time = |bit: u32| (start as f64 + bit as f64 * period) as u32;
+        let sample = |bit: u32| line.level_at((start as f64 + (bit as f64 + 0.5) * period) as u32);
+
+        // A start bit shorter than half a period is a glitch
+        if sample(0) != Some(false) {
+            continue;
+        }
+        annotations.push(Annotation::new(row, start, time(1), AnnotationKind::Start));
+
+        let data_bits = config.data_bits as u32;
+        let mut value = 0u64;
+        let mut undefined = false;
+        for i in 0..data_bits {
+            match sample(1 + i) {
+                Some(bit) => value |= (bit as u64) << i,
+                None => undefined = true,
+            }
+        }
+
+        let mut bit = 1 + data_bits;
+        if undefined {
+            annotations.push(Annotation::error(
+                row,
+                time(1),
+                time(bit),
+                "Undefined data bit",
+            ));
+        } else {
+            annotations.push(Annotation::new(
+                row,
+                time(1),
+                time(bit),
+                AnnotationKind::Data { value },
+            ));
+        }
+
+        if config.parity != Parity::None {
+            let odd_ones = value.count_ones() % 2 == 1;
+            let expected = match config.parity {
+                Parity::Even => odd_ones,
+                _ => !odd_ones,
+            };
+            if sample(bit) != Some(expected) {
+                annotations.push(Annotation::error(
+                    row,
+                    time(bit),
+                    time(bit + 1),
+                    "Parity error",
+                ));
+            }
+            bit += 1;
+        }
+
+        let stop_start = bit;
+        for _ in 0..config.stop_bits {
+            if sample(bit) != Some(true) {
+                annotations.push(Annotation::error(
+                    row,
+                    time(bit),
+                    time(bit + 1),
+                    "Framing error",
+                ));
+            }
+            bit += 1;
+        }
+        annotations.push(Annotation::new(
+            row,
+            time(stop_start),
+            time(bit),
+            AnnotationKind::Stop,
+        ));
+
+        // The next start bit cannot begin before the middle of the last stop bit
+        resume = (start as f64 + (bit as f64 - 0.5) * period) as u32;
+    }
+
+    annotations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::signal;
+
+    fn config(parity: Parity) -> UartConfig {
+        UartConfig {
+            signal: "rx".to_owned(),
+            baud_rate: 1,
+            data_bits: 8,
+            parity,
+            stop_bits: 1,
+        }
+    }
+
+    /// Builds the levels of a frame starting at `start`, with 10 ticks per bit
+    fn frame(start: u32, bits: &[bool]) -> Vec<(u32, bool)> {
+        let mut levels = vec![(start, false)];
+        for (i, b) in bits.iter().enumerate() {
+            levels.push((start + 10 * (i as u32 + 1), *b));
+        }
+        levels
+    }
+
+    #[test]
+    fn decode_byte() {
+        // 0xA5, LSB first, then stop bit
+        let mut levels = vec![(0, true)];
+        levels.extend(frame(
+            100,
+            &[true, false, true, false, false, true, false, true, true],
+        ));
+        let res = decode_line(&signal(&levels), 10.0, &config(Parity::None));
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].kind, AnnotationKind::Start);
+        assert_eq!(res[1].kind, AnnotationKind::Data { value: 0xA5 });
+        assert_eq!((res[1].start, res[1].end), (110, 190));
+        assert_eq!(res[2].kind, AnnotationKind::Stop);
+    }
+
+    #[test]
+    fn parity_and_framing_errors() {
+        // 0x01 with a wrong even parity bit and a missing stop bit
+        let mut levels = vec![(0, true)];
+        levels.extend(frame(
+            100,
+            &[
+                true, false, false, false, false, false, false, false, false, false,
+            ],
+        ));
+        levels.push((210, true));
+        let res = decode_line(&signal(&levels), 10.0, &config(Parity::Even));
+
+        let errors = res
+            .iter()
+            .filter_map(|a| match &a.kind {
+                AnnotationKind::Error { message } => Some(message.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec!["Parity error", "Framing error"]);
+    }
+
+    #[test]
+    fn invalid_data_bits() {
+        for data_bits in [0, 65] {
+            let config = UartConfig {
+                data_bits,
+                ..config(Parity::None)
+            };
+            assert!(decode(&VCDFile::default(), &config).is_err());
+        }
+    }
+}
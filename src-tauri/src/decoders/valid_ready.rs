@@ -0,0 +1,73 @@
+//! Valid/ready stream decoder, emitting a transaction per handshake.
+
+use super::bus::{hex, BusDecoder, Sample, Transaction};
+
+#[derive(Debug, Default)]
+pub struct ValidReadyDecoder {
+    /// Edge at which the current beat was first offered
+    pending_since: Option<u32>,
+}
+
+impl BusDecoder for ValidReadyDecoder {
+    fn required_signals(&self) -> &'static [&'static str] {
+        &["valid", "ready"]
+    }
+
+    fn optional_signals(&self) -> &'static [&'static str] {
+        &["data", "last"]
+    }
+
+    fn sample(&mut self, time: u32, values: &Sample, transactions: &mut Vec<Transaction>) {
+        if !values.high("valid") {
+            self.pending_since = None;
+            return;
+        }
+
+        let start = *self.pending_since.get_or_insert(time);
+        if values.high("ready") {
+            let mut label = if values.get("data").is_some() || values.high("last") {
+                hex(values.get("data"))
+            } else {
+                "transfer".to_owned()
+            };
+            if values.high("last") {
+                label.push_str(" (last)");
+            }
+
+            transactions.push(Transaction {
+                start,
+                end: time,
+                label,
+            });
+            self.pending_since = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::bus::run;
+
+    #[test]
+    fn stalled_beat() {
+        let res = run(
+            &mut ValidReadyDecoder::default(),
+            &[
+                ("valid", &[(0, 0), (12, 1), (42, 0)]),
+                ("ready", &[(0, 0), (32, 1)]),
+                ("data", &[(0, 0), (12, 0xab), (42, 0)]),
+            ],
+            5,
+        );
+
+        assert_eq!(
+            res,
+            vec![Transaction {
+                start: 20,
+                end: 40,
+                label: "0xab".to_owned()
+            },]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Wishbone classic decoder, emitting a transaction per acknowledged (or errored) cycle.
+
+use super::bus::{hex, BusDecoder, Sample, Transaction};
+
+#[derive(Debug, Default)]
+pub struct WishboneDecoder {
+    /// Edge at which the current request started
+    request_since: Option<u32>,
+}
+
+impl BusDecoder for WishboneDecoder {
+    fn required_signals(&self) -> &'static [&'static str] {
+        &["cyc", "stb", "we", "ack"]
+    }
+
+    fn optional_signals(&self) -> &'static [&'static str] {
+        &["adr", "dat_w", "dat_r", "err"]
+    }
+
+    fn sample(&mut self, time: u32, values: &Sample, transactions: &mut Vec<Transaction>) {
+        if !values.high("cyc") || !values.high("stb") {
+            self.request_since = None;
+            return;
+        }
+
+        let start = *self.request_since.get_or_insert(time);
+        let address = hex(values.get("adr"));
+        let label = if values.high("err") {
+            format!("ERROR {address}")
+        } else if !values.high("ack") {
+            return;
+        } else if values.high("we") {
+            format!("WRITE {address} = {}", hex(values.get("dat_w")))
+        } else {
+            format!("READ {address} = {}", hex(values.get("dat_r")))
+        };
+
+        transactions.push(Transaction {
+            start,
+            end: time,
+            label,
+        });
+        self.request_since = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decoders::bus::run;
+
+    #[test]
+    fn write_then_read() {
+        let res = run(
+            &mut WishboneDecoder::default(),
+            &[
+                ("cyc", &[(0, 0), (12, 1), (52, 0)]),
+                ("stb", &[(0, 0), (12, 1), (52, 0)]),
+                ("we", &[(0, 1), (32, 0)]),
+                ("adr", &[(0, 0x10), (32, 0x14)]),
+                ("dat_w", &[(0, 0xcafe)]),
+                ("dat_r", &[(0, 0), (42, 0xbeef)]),
+                ("ack", &[(0, 0), (22, 1), (32, 0), (42, 1), (52, 0)]),
+            ],
+            6,
+        );
+
+        assert_eq!(
+            res,
+            vec![
+                Transaction {
+                    start: 20,
+                    end: 30,
+                    label: "WRITE 0x10 = 0xcafe".to_owned()
+                },
+                Transaction {
+                    start: 40,
+                    end: 50,
+                    label: "READ 0x14 = 0xbeef".to_owned()
+                },
+            ]
+        );
+    }
+}
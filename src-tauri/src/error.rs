@@ -13,6 +13,10 @@ pub enum Error {
     Tauri(#[serde(serialize_with = "error_to_string")] tauri::Error),
     Other(String),
     NoProject,
+    /// The process was killed on request
+    Cancelled,
+    /// The process was killed after exceeding a time limit
+    TimeLimit(String),
     None,
 }
 
@@ -0,0 +1,392 @@
+//! History of the simulations, keeping a copy of the dumps and transcript of each run in the
+//! `history` directory of the output directory, so that they are not overwritten by the next run.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    icarus::{
+        cache,
+        transcript::TranscriptLine,
+        vcd::VCDFile,
+        vvp::{dump_paths, SimulationRecord},
+    },
+    project::Project,
+    state::AppState,
+};
+
+const ENTRY_FILE: &str = "run.json";
+const TRANSCRIPT_FILE: &str = "transcript.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryConfig {
+    /// Number of runs kept, the oldest ones being removed after each simulation. The history is
+    /// disabled if 0.
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_keep() -> usize {
+    20
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            keep: default_keep(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    /// Start time, in milliseconds since the UNIX epoch
+    pub timestamp: u64,
+    pub target: String,
+    /// Top-level modules the executable was elaborated with, all the uninstantiated ones if empty
+    pub roots: Vec<String>,
+    /// Arguments of `iverilog` the executable was compiled with, setting the parameters and macros
+    pub compiler_args: Vec<String>,
+    /// Arguments of `vvp`
+    pub simulator_args: Vec<String>,
+    pub seed: Option<u32>,
+    pub exit_code: Option<i32>,
+    /// Whether the simulation was stopped by the time limit
+    pub timed_out: bool,
+    /// Hash of the sources the executable was compiled from, to tell whether they changed between
+    /// runs. Unknown if the executable was not compiled by PaluSim.
+    pub source_hash: Option<String>,
+    /// Hash of the sources and options the executable was compiled from
+    pub compilation_key: Option<String>,
+    /// Dumps, relative to the directory of the entry
+    pub dumps: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedRun {
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+    pub transcript: Vec<TranscriptLine>,
+    pub waves: Vec<VCDFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum SignalDifference {
+    OnlyInFirst {
+        path: String,
+    },
+    OnlyInSecond {
+        path: String,
+    },
+    /// The values of the signal differ from `time`
+    Values {
+        path: String,
+        time: u32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunComparison {
+    pub first: HistoryEntry,
+    pub second: HistoryEntry,
+    pub same_sources: bool,
+    /// Index of the first line of the transcripts which differs, if any
+    pub transcript_difference: Option<usize>,
+    /// Differences between the dumps with the same file name
+    pub signals: Vec<SignalDifference>,
+}
+
+fn history_directory(project: &Project) -> Result<PathBuf, Error> {
+    Ok(project.output_directory()?.join("history"))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, Error> {
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| Error::Other(format!("Could not parse {}: {e}", path.display())))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::Other(format!("Could not serialize {}: {e}", path.display())))?;
+    Ok(fs::write(path, content)?)
+}
+
+/// Stores a finished simulation in the history of `project`, removing the oldest runs
+pub fn save(
+    project: &Project,
+    record: &SimulationRecord,
+    transcript: &[TranscriptLine],
+    exit_code: Option<i32>,
+    timed_out: bool,
+) -> Result<Option<HistoryEntry>, Error> {
+    if project.history.keep == 0 {
+        return Ok(None);
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let id = format!("{timestamp}-{}", record.run);
+    let directory = history_directory(project)?.join(&id);
+    fs::create_dir_all(&directory)?;
+
+    let mut dumps = vec![];
+    for dump in dump_paths(transcript, &record.directory) {
+        let name = PathBuf::from(dump.file_name().unwrap_or_default());
+        fs::copy(&dump, directory.join(&name))?;
+        dumps.push(name);
+    }
+
+    // The target may have been modified since the compilation
+    let build = cache::build(&record.target.executable(&record.directory));
+    let entry = HistoryEntry {
+        id,
+        timestamp,
+        target: record.target.name.clone(),
+        roots: build.as_ref().map(|b| b.roots.clone()).unwrap_or_default(),
+        compiler_args: build
+            .as_ref()
+            .map(|b| b.arguments.clone())
+            .unwrap_or_default(),
+        simulator_args: record.arguments.clone(),
+        seed: record.seed,
+        exit_code,
+        timed_out,
+        source_hash: build.as_ref().map(|b| b.sources.clone()),
+        compilation_key: build.map(|b| b.key),
+        dumps,
+    };
+    write_json(&directory.join(ENTRY_FILE), &entry)?;
+    write_json(&directory.join(TRANSCRIPT_FILE), &transcript)?;
+
+    prune(project, project.history.keep)?;
+    Ok(Some(entry))
+}
+
+/// Lists the runs of the history, the most recent first
+pub fn list(project: &Project) -> Result<Vec<HistoryEntry>, Error> {
+    let directory = history_directory(project)?;
+    if !directory.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = vec![];
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path().join(ENTRY_FILE);
+        match read_json::<HistoryEntry>(&path) {
+            Ok(entry) => entries.push(entry),
+            // Skips the runs being written or written by another version
+            Err(e) => tracing::warn!("{e:?}"),
+        }
+    }
+    entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.id.cmp(&a.id)));
+    Ok(entries)
+}
+
+/// Removes the runs of the history but the `keep` most recent ones, returning their ids
+pub fn prune(project: &Project, keep: usize) -> Result<Vec<String>, Error> {
+    let directory = history_directory(project)?;
+    let mut removed = vec![];
+    for entry in list(project)?.into_iter().skip(keep) {
+        fs::remove_dir_all(directory.join(&entry.id))?;
+        removed.push(entry.id);
+    }
+    Ok(removed)
+}
+
+fn entry(project: &Project, id: &str) -> Result<HistoryEntry, Error> {
+    let path = history_directory(project)?.join(id).join(ENTRY_FILE);
+    if !path.is_file() {
+        return Err(Error::Other(format!("No run {id} in the history")));
+    }
+    read_json(&path)
+}
+
+fn read_waves(project: &Project, entry: &HistoryEntry) -> Result<Vec<(PathBuf, VCDFile)>, Error> {
+    let directory = history_directory(project)?.join(&entry.id);
+    entry
+        .dumps
+        .iter()
+        // Only the VCD dumps can be displayed
+        .filter(|dump| dump.extension().is_some_and(|e| e == "vcd"))
+        .map(|dump| {
+            let vcd = VCDFile::from_str(&fs::read_to_string(directory.join(dump))?)
+                .map_err(|_| Error::Other("Could not parse VCD file".to_owned()))?;
+            Ok((dump.clone(), vcd))
+        })
+        .collect()
+}
+
+/// Lists the differences between the signals of two dumps
+pub fn compare_dumps(first: &VCDFile, second: &VCDFile) -> Vec<SignalDifference> {
+    let mut differences = vec![];
+    for (path, variable) in first.variables() {
+        let Some(other) = second.find_variable(&path) else {
+            differences.push(SignalDifference::OnlyInFirst { path });
+            continue;
+        };
+
+        let changes = first.changes(variable.identifier);
+        let other_changes = second.changes(other.identifier);
+        let mismatch = changes
+            .iter()
+            .zip(&other_changes)
+            .find(|(a, b)| a != b)
+            .map(|(a, b)| a.0.min(b.0))
+            .or_else(|| {
+                // One of the signals has more changes than the other
+                let common = changes.len().min(other_changes.len());
+                changes
+                    .get(common)
+                    .or(other_changes.get(common))
+                    .map(|c| c.0)
+            });
+        if let Some(time) = mismatch {
+            differences.push(SignalDifference::Values { path, time });
+        }
+    }
+    for (path, _) in second.variables() {
+        if first.find_variable(&path).is_none() {
+            differences.push(SignalDifference::OnlyInSecond { path });
+        }
+    }
+    differences
+}
+
+/// Lists the simulation runs of the history, the most recent first
+#[tauri::command]
+pub fn list_runs(state: AppState<'_>) -> Result<Vec<HistoryEntry>, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    list(&project)
+}
+
+/// Loads the dumps of a run of the history, to display them in place of the last simulation
+#[tauri::command]
+pub fn open_run(id: String, state: AppState<'_>) -> Result<OpenedRun, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    let entry = entry(&project, &id)?;
+    let transcript = read_json(&history_directory(&project)?.join(&id).join(TRANSCRIPT_FILE))?;
+    let waves = read_waves(&project, &entry)?
+        .into_iter()
+        .map(|(_, vcd)| vcd)
+        .collect::<Vec<_>>();
+
+    *state.lock().unwrap().waves_mut() = waves.clone();
+    Ok(OpenedRun {
+        entry,
+        transcript,
+        waves,
+    })
+}
+
+/// Compares the options, transcripts and dumps of two runs of the history
+#[tauri::command]
+pub fn compare_runs(
+    first: String,
+    second: String,
+    state: AppState<'_>,
+) -> Result<RunComparison, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    let directory = history_directory(&project)?;
+    let (first, second) = (entry(&project, &first)?, entry(&project, &second)?);
+
+    let transcripts = [&first, &second]
+        .map(|e| read_json::<Vec<TranscriptLine>>(&directory.join(&e.id).join(TRANSCRIPT_FILE)));
+    let [first_transcript, second_transcript] = transcripts;
+    let (first_transcript, second_transcript) = (first_transcript?, second_transcript?);
+    let transcript_difference = (0..first_transcript.len().max(second_transcript.len()))
+        .find(|i| first_transcript.get(*i) != second_transcript.get(*i));
+
+    let second_waves = read_waves(&project, &second)?;
+    let mut signals = vec![];
+    for (name, vcd) in read_waves(&project, &first)? {
+        if let Some((_, other)) = second_waves.iter().find(|(n, _)| *n == name) {
+            signals.extend(compare_dumps(&vcd, other));
+        }
+    }
+
+    Ok(RunComparison {
+        same_sources: first.source_hash.is_some() && first.source_hash == second.source_hash,
+        first,
+        second,
+        transcript_difference,
+        signals,
+    })
+}
+
+/// Removes the runs of the history but the `keep` most recent ones, or the number of the project
+/// configuration, returning their ids
+#[tauri::command]
+pub fn prune_runs(keep: Option<usize>, state: AppState<'_>) -> Result<Vec<String>, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    prune(&project, keep.unwrap_or(project.history.keep))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    fn vcd(changes: &str) -> VCDFile {
+        test_dump(
+            "top",
+            &["wire 1 ! clk", "wire 4 @ count"],
+            &format!("#0\n0!\nb0 @\n{changes}"),
+        )
+    }
+
+    #[test]
+    fn dump_differences() {
+        let first = vcd("#5\n1!\nb1 @\n#10\n0!\n#15\n1!\nb10 @\n");
+        let second = vcd("#5\n1!\nb1 @\n#10\n0!\n#15\n1!\nb11 @\n#20\n0!\n");
+
+        assert_eq!(
+            compare_dumps(&first, &second),
+            vec![
+                SignalDifference::Values {
+                    path: "top.clk".to_owned(),
+                    time: 20
+                },
+                SignalDifference::Values {
+                    path: "top.count".to_owned(),
+                    time: 15
+                },
+            ]
+        );
+        assert!(compare_dumps(&first, &first).is_empty());
+    }
+}
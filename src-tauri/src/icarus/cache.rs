@@ -0,0 +1,244 @@
+//! Incremental builds, skipping the compilations whose sources and options did not change since
+//! the executable was produced, and optionally the simulations of an unchanged executable.
+//!
+//! The inputs of a compilation are stored next to its executable, with the `.build.json` suffix,
+//! and the last simulation of an executable with the `.simulation.json` suffix.
+
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    transcript::TranscriptLine,
+    vvp::{dump_paths, SimulationRecord},
+};
+use crate::{
+    error::Error,
+    project::Project,
+    util::{hash_files, Digest},
+    verilog::includes,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheConfig {
+    /// Whether the compilations are skipped when the executable is up to date
+    #[serde(default = "default_compilation")]
+    pub compilation: bool,
+    /// Whether the simulations of an unchanged executable with the same options reuse the
+    /// transcript and dumps of the previous one
+    #[serde(default)]
+    pub simulation: bool,
+}
+
+fn default_compilation() -> bool {
+    true
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            compilation: default_compilation(),
+            simulation: false,
+        }
+    }
+}
+
+/// Inputs an executable was compiled from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Build {
+    /// Hash of the sources, roots and arguments
+    pub key: String,
+    /// Hash of the sources and the files they include
+    pub sources: String,
+    /// Top-level modules elaborated, all the uninstantiated ones if empty
+    pub roots: Vec<String>,
+    /// Arguments of `iverilog` setting the parameters and macros
+    pub arguments: Vec<String>,
+}
+
+/// Last successful simulation of an executable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedSimulation {
+    key: String,
+    transcript: Vec<TranscriptLine>,
+    /// Hash of the dumps it produced, to detect when they are overwritten by another simulation
+    dumps: String,
+}
+
+fn with_suffix(executable: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(executable);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Adds to `files` the files they include, recursively. Includes are looked up relative to the
+/// including file, then to the project directory, and ignored if they are not found.
+pub fn dependencies(files: &[PathBuf], project: &Project) -> Vec<PathBuf> {
+    let mut dependencies = files.iter().cloned().collect::<BTreeSet<_>>();
+    let mut pending = files.to_vec();
+    while let Some(file) = pending.pop() {
+        let Ok(source) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for include in includes(&source) {
+            let candidates = [
+                file.parent().map(|p| p.join(&include)),
+                Some(project.project_directory.join(&include)),
+            ];
+            if let Some(path) = candidates.into_iter().flatten().find(|p| p.is_file()) {
+                if dependencies.insert(path.clone()) {
+                    pending.push(path);
+                }
+            }
+        }
+    }
+    dependencies.into_iter().collect()
+}
+
+impl Build {
+    /// Hashes the sources, included files and options of a compilation
+    pub fn new(
+        project: &Project,
+        files: &[PathBuf],
+        roots: &[String],
+        arguments: &[String],
+    ) -> Result<Self, Error> {
+        let sources = hash_files(&dependencies(files, project))?;
+        Ok(Self {
+            key: Digest::default()
+                .update(&sources)
+                .update_all(roots)
+                .update_all(arguments)
+                .finish(),
+            sources,
+            roots: roots.to_vec(),
+            arguments: arguments.to_vec(),
+        })
+    }
+}
+
+/// Returns the inputs `executable` was compiled from, if it was compiled by PaluSim
+pub fn build(executable: &Path) -> Option<Build> {
+    serde_json::from_str(&fs::read_to_string(with_suffix(executable, ".build.json")).ok()?).ok()
+}
+
+/// Whether `executable` exists and was compiled from the same inputs as `build`
+pub fn is_up_to_date(executable: &Path, build: &Build) -> bool {
+    executable.is_file() && self::build(executable).is_some_and(|b| b.key == build.key)
+}
+
+/// Records that `executable` was compiled from `build`
+pub fn store_compilation(executable: &Path, build: &Build) -> Result<(), Error> {
+    let content = serde_json::to_string(build)
+        .map_err(|e| Error::Other(format!("Could not serialize the build: {e}")))?;
+    Ok(fs::write(with_suffix(executable, ".build.json"), content)?)
+}
+
+/// Forgets the compilation and simulation of `executable`, so that both are run again
+pub fn invalidate(executable: &Path) {
+    for suffix in [".build.json", ".simulation.json"] {
+        let path = with_suffix(executable, suffix);
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Could not remove {path:?}: {e:?}");
+            }
+        }
+    }
+}
+
+/// Hashes the executable and options of a simulation
+fn simulation_key(project: &Project, record: &SimulationRecord) -> Result<String, Error> {
+    Ok(Digest::default()
+        .update(fs::read(record.target.executable(&record.directory))?)
+        .update(record.directory.to_string_lossy().as_bytes())
+        .update_all(&record.arguments)
+        .update(format!("{:?}", project.limits.wall_clock))
+        .finish())
+}
+
+/// Returns the transcript of the previous simulation with the same executable and options, if
+/// its dumps were not modified since
+pub fn cached_simulation(
+    project: &Project,
+    record: &SimulationRecord,
+) -> Option<Vec<TranscriptLine>> {
+    let executable = record.target.executable(&record.directory);
+    let cached: CachedSimulation = serde_json::from_str(
+        &fs::read_to_string(with_suffix(&executable, ".simulation.json")).ok()?,
+    )
+    .ok()?;
+
+    let dumps = dump_paths(&cached.transcript, &record.directory);
+    let valid = cached.key == simulation_key(project, record).ok()?
+        && hash_files(&dumps).is_ok_and(|h| h == cached.dumps);
+    valid.then_some(cached.transcript)
+}
+
+/// Records a successful simulation, to reuse it while the executable and options do not change
+pub fn store_simulation(
+    project: &Project,
+    record: &SimulationRecord,
+    transcript: &[TranscriptLine],
+) -> Result<(), Error> {
+    let cached = CachedSimulation {
+        key: simulation_key(project, record)?,
+        transcript: transcript.to_vec(),
+        dumps: hash_files(&dump_paths(transcript, &record.directory))?,
+    };
+    let content = serde_json::to_string(&cached)
+        .map_err(|e| Error::Other(format!("Could not serialize the simulation: {e}")))?;
+    let executable = record.target.executable(&record.directory);
+    Ok(fs::write(
+        with_suffix(&executable, ".simulation.json"),
+        content,
+    )?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn include_dependencies() {
+        let directory = std::env::temp_dir().join("palusim_cache");
+        fs::create_dir_all(directory.join("common")).unwrap();
+        fs::write(
+            directory.join("top.v"),
+            "`include \"common/bus.vh\"\n`include \"missing.vh\"\nmodule top; endmodule\n",
+        )
+        .unwrap();
+        fs::write(directory.join("common/bus.vh"), "`include \"defs.vh\"\n").unwrap();
+        fs::write(directory.join("defs.vh"), "`define WIDTH 8\n").unwrap();
+
+        let project = Project {
+            project_directory: directory.clone(),
+            ..serde_json::from_str("{}").unwrap()
+        };
+        let files = [directory.join("top.v")];
+        assert_eq!(
+            dependencies(&files, &project),
+            vec![
+                directory.join("common/bus.vh"),
+                directory.join("defs.vh"),
+                directory.join("top.v"),
+            ]
+        );
+
+        let build = Build::new(&project, &files, &[], &[]).unwrap();
+        assert_eq!(build, Build::new(&project, &files, &[], &[]).unwrap());
+        fs::write(directory.join("defs.vh"), "`define WIDTH 16\n").unwrap();
+        let changed = Build::new(&project, &files, &[], &[]).unwrap();
+        assert_ne!(build.sources, changed.sources);
+        assert_ne!(build.key, changed.key);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
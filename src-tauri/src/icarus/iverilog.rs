@@ -5,12 +5,25 @@ use regex::Regex;
 use serde::Serialize;
 use std::{
     collections::HashMap,
+    fs,
     path::{Path, PathBuf},
     process::Command,
+    sync::Mutex,
 };
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 
-use crate::{consts::IVERILOG_EXE, error::Error, project::ProjectEntry, state::AppState};
+use super::{
+    cache::{self, Build},
+    top::root_modules,
+    transcript::{run_streaming, Stream},
+};
+use crate::{
+    consts::IVERILOG_EXE,
+    error::Error,
+    jobs::{self, JobKind},
+    project::{Project, Target},
+    state::{AppState, State},
+};
 
 lazy_static! {
     /// Matches a string with the format `main.verilog:5: syntax error`
@@ -19,36 +32,164 @@ lazy_static! {
     static ref COMPILATION_OUTPUT_IGNORED: Regex = Regex::new("^\\d+ error\\(s\\) during elaboration\\.|I give up\\.$").unwrap();
 }
 
+/// Last line printed by the simulations finished by the simulated time limit of the project
+pub const TIME_LIMIT_MESSAGE: &str = "PaluSim: simulated time limit reached";
+const TIME_LIMIT_MODULE: &str = "palusim_time_limit";
+
+/// Starts compiling a target of the project in the background, the default one if `target` is not
+/// given, returning the id of the job. The compilation is skipped if the executable is up to date,
+/// unless `force` is set.
 #[tauri::command]
-pub fn compile(state: AppState<'_>, app: AppHandle) -> Result<CompilationOutcome, Error> {
-    fn extract_pathes(entries: &[ProjectEntry]) -> Vec<&Path> {
-        entries.iter().fold(vec![], |mut acc, e| {
-            if e.children.is_empty() && !e.path.is_dir() {
-                acc.push(&e.path)
+pub fn compile(
+    target: Option<String>,
+    force: Option<bool>,
+    state: AppState<'_>,
+    app: AppHandle,
+) -> Result<u64, Error> {
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    let target = project.target(target.as_deref())?;
+    if force.unwrap_or(false) {
+        cache::invalidate(&target.executable(&project.output_directory()?));
+    }
+    Ok(jobs::spawn(
+        JobKind::Compilation,
+        &mut state,
+        &app,
+        move |run, state, app| {
+            compile_project(
+                &project,
+                &target,
+                &project.output_directory()?,
+                run,
+                state,
+                app,
+            )
+        },
+    ))
+}
+
+/// Compiles `target` as the run `run`, writing the executable in `output_directory`. The state
+/// must not be locked by the caller, so that the compilation can be cancelled.
+pub fn compile_project(
+    project: &Project,
+    target: &Target,
+    output_directory: &Path,
+    run: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<CompilationOutcome, Error> {
+    jobs::progress(run, "Collecting sources", state, app);
+    let mut files = target.source_files(project)?;
+    let mut warnings = vec![];
+    let mut roots = target.top.iter().cloned().collect::<Vec<_>>();
+    let mut top = target.top.clone();
+    if roots.is_empty() {
+        let candidates = root_modules(&files)?;
+        if candidates.len() == 1 {
+            top = Some(candidates[0].name.clone());
+        } else if candidates.len() > 1 {
+            let names = candidates
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            // Elaborates only the most likely testbench, unless it cannot be told apart
+            if candidates[0].score > candidates[1].score {
+                roots.push(candidates[0].name.clone());
+                top = Some(candidates[0].name.clone());
+                warnings.push(format!(
+                    "Several top-level modules found ({names}), using {}",
+                    candidates[0].name
+                ));
             } else {
-                acc.append(&mut extract_pathes(&e.children))
+                warnings.push(format!(
+                    "Several top-level modules found ({names}), set the top module of the target"
+                ));
             }
-            acc
-        })
+        }
+    }
+    if let Some(limit) = &project.limits.simulated_time {
+        files.push(time_limit_module(output_directory, limit)?);
+        if !roots.is_empty() {
+            roots.push(TIME_LIMIT_MODULE.to_owned());
+        }
     }
 
-    if let Some(project) = state.lock().unwrap().project() {
-        let temp = project.read_project_tree(true)?;
-        compile_inner(
-            &extract_pathes(&temp.children),
-            &project.output_directory()?,
-            app,
-        )
-    } else {
-        Err(Error::NoProject)
+    // The parameters which are not prefixed by a module are the ones of the detected top module
+    let arguments = Target {
+        top,
+        ..target.clone()
+    }
+    .compiler_args()?;
+    let executable = target.executable(output_directory);
+    let build = Build::new(project, &files, &roots, &arguments)?;
+    if project.cache.compilation && cache::is_up_to_date(&executable, &build) {
+        tracing::info!("{executable:?} is up to date");
+        return Ok(CompilationOutcome::Success {
+            warnings,
+            cached: true,
+        });
     }
+    // The executable may be left incomplete if the compilation is cancelled
+    cache::invalidate(&executable);
+
+    app.emit_all("compilation-started", run)?;
+    jobs::progress(run, "Compiling", state, app);
+    let mut outcome = compile_inner(
+        &files.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+        &roots,
+        &arguments,
+        &executable,
+        run,
+        state,
+        app.clone(),
+    )?;
+    if let CompilationOutcome::Success { warnings: w, .. } = &mut outcome {
+        w.append(&mut warnings);
+        cache::store_compilation(&executable, &build)?;
+    }
+    Ok(outcome)
+}
+
+/// Writes a module finishing the simulation once `limit` (e.g. `10ms`) of simulated time has
+/// elapsed, returning its path
+fn time_limit_module(output_directory: &Path, limit: &str) -> Result<PathBuf, Error> {
+    lazy_static! {
+        static ref TIME: Regex = Regex::new("^\\s*(\\d+)\\s*(s|ms|us|ns|ps|fs)\\s*$").unwrap();
+    }
+
+    let cap = TIME
+        .captures(limit)
+        .ok_or_else(|| Error::Other(format!("Invalid simulated time limit {limit}")))?;
+    let path = output_directory.join("palusim_time_limit.v");
+    let module = format!(
+        r#"`timescale 1{unit}/1{unit}
+module {TIME_LIMIT_MODULE};
+  initial begin
+    #{value};
+    $display("{TIME_LIMIT_MESSAGE}");
+    $finish;
+  end
+endmodule
+"#,
+        value = &cap[1],
+        unit = &cap[2]
+    );
+    fs::write(&path, module)?;
+    Ok(path)
 }
 
 /// Outcome of the compilation, containing status and errors, or a handle to run a simulation
 #[derive(Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase", tag = "status")]
 pub enum CompilationOutcome {
-    Success {},
+    Success {
+        /// Problems of the project which did not prevent the compilation
+        warnings: Vec<String>,
+        /// Whether the executable was up to date, and the compilation skipped
+        cached: bool,
+    },
     Failure {
         /// Mapping of errors, grouped by files, then lines
         errors: ErrorMap,
@@ -125,24 +266,30 @@ fn parse_compilation_output(out: &str) -> Result<ErrorMap, Error> {
 
 /// Compiles verilog files
 /// - `files`: The complete list of files to be used for the compilation
-/// - `output_directory`: The path of a directory to use for compilation results and cache
+/// - `roots`: The top-level modules to elaborate, all the uninstantiated ones if empty
+/// - `arguments`: Additional arguments, such as parameter overrides
+/// - `output_executable`: The path of the executable to produce
 ///
 /// Note: For correct output parsing, the files' path should not contain colons.
-#[tracing::instrument(name = "compilation")]
+#[tracing::instrument(name = "compilation", skip(state))]
 pub fn compile_inner(
     files: &[&Path],
-    output_directory: &Path,
+    roots: &[String],
+    arguments: &[String],
+    output_executable: &Path,
+    run: u64,
+    state: &Mutex<State>,
     app: AppHandle,
 ) -> Result<CompilationOutcome, Error> {
     tracing::info!("Starting compilation");
     tracing::debug!(
-        "output directory: {:?}, files: {:?}",
-        output_directory,
+        "output executable: {:?}, roots: {:?}, arguments: {:?}, files: {:?}",
+        output_executable,
+        roots,
+        arguments,
         files.iter().collect::<Vec<_>>()
     );
 
-    let output_executable = PathBuf::from(output_directory).join("a.out");
-
     let iverilog = PathBuf::from(
         app.path_resolver()
             .resolve_resource(IVERILOG_EXE)
@@ -153,23 +300,43 @@ pub fn compile_inner(
         directory = path.to_string();
     }
 
-    let compilation_output = Command::new(iverilog)
+    let mut command = Command::new(iverilog);
+    command
         .current_dir(directory)
         .arg("-o")
-        .arg(output_executable.clone())
-        .args(files)
-        .output()?;
+        .arg(output_executable);
+    for root in roots {
+        command.arg("-s").arg(root);
+    }
+    command.args(arguments).args(files);
+    let compilation_output = run_streaming(command, run, "compilation-output", None, state, &app)?;
+    state
+        .lock()
+        .unwrap()
+        .set_transcript(run, compilation_output.transcript.clone());
 
     tracing::info!(
         "iverilog exited with {:?}",
         compilation_output.status.code()
     );
 
-    if compilation_output.status.success() {
-        Ok(CompilationOutcome::Success {})
+    if compilation_output.stop.is_some() {
+        Err(Error::Cancelled)
+    } else if compilation_output.status.success() {
+        Ok(CompilationOutcome::Success {
+            warnings: vec![],
+            cached: false,
+        })
     } else {
+        let stderr = compilation_output
+            .transcript
+            .iter()
+            .filter(|line| line.stream == Stream::Stderr)
+            .map(|line| line.line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
         Ok(CompilationOutcome::Failure {
-            errors: parse_compilation_output(&String::from_utf8_lossy(&compilation_output.stderr))?,
+            errors: parse_compilation_output(&stderr)?,
         })
     }
 }
@@ -325,4 +492,15 @@ mod test {
             .global
             .contains(&"Filename cannot contains colons".to_owned()));
     }
+
+    #[test]
+    fn test_time_limit_module() {
+        let directory = std::env::temp_dir();
+        assert!(time_limit_module(&directory, "10 s0").is_err());
+
+        let path = time_limit_module(&directory, "15ms").unwrap();
+        let module = std::fs::read_to_string(path).unwrap();
+        assert!(module.starts_with("`timescale 1ms/1ms\n"));
+        assert!(module.contains("    #15;\n"));
+    }
 }
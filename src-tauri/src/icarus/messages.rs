@@ -0,0 +1,188 @@
+//! Structured messages of the simulations, parsed from the output of the severity tasks
+//! (`$info`, `$warning`, `$error`, `$fatal`), of `$finish`/`$stop` and of the dumpers.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::transcript::TranscriptLine;
+use crate::{error::Error, state::AppState};
+
+lazy_static! {
+    /// Matches `ERROR: tb.v:42: message`, the location being absent when printed by `$display`
+    static ref SEVERITY: Regex = Regex::new("^(INFO|WARNING|ERROR|FATAL):\\s*(?:(\\S+?):(\\d+):)?\\s*(.*)$").unwrap();
+    /// Matches the line following a severity task, `       Time: 50 Scope: tb`
+    static ref SEVERITY_TIME: Regex = Regex::new("^\\s+Time:\\s*(\\d+)(?:\\s*\\(?(\\d*\\s*[munpf]?s)\\)?)?\\s+Scope:\\s*(.+?)\\.?\\s*$").unwrap();
+    /// Matches `tb.v:20: $finish called at 100 (1ns)`
+    static ref FINISH: Regex = Regex::new("^(?:(\\S+?):(\\d+): )?\\$(finish|stop) called at (\\d+) \\((\\w+)\\)$").unwrap();
+    /// Matches `VCD warning: message`
+    static ref DUMPER: Regex = Regex::new("^(VCD|FST|LXT2?) (info|warning|error): (.*)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Fatal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationMessage {
+    pub severity: Severity,
+    pub message: String,
+    /// Simulation time at which the message was printed, in multiples of `unit` if given
+    pub time: Option<u64>,
+    /// Time unit, such as `1ns` or `10ps`
+    pub unit: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub scope: Option<String>,
+}
+
+impl SimulationMessage {
+    fn new(severity: Severity, message: &str) -> Self {
+        Self {
+            severity,
+            message: message.to_owned(),
+            time: None,
+            unit: None,
+            file: None,
+            line: None,
+            scope: None,
+        }
+    }
+}
+
+/// Extracts the messages of a simulation transcript, in the order they were printed
+pub fn parse_messages(transcript: &[TranscriptLine]) -> Vec<SimulationMessage> {
+    let mut messages: Vec<SimulationMessage> = vec![];
+    // Whether the last message comes from a severity task, hence may be followed by its time
+    let mut awaiting_time = false;
+
+    for line in transcript.iter().map(|l| l.line.as_str()) {
+        if let Some(cap) = SEVERITY_TIME.captures(line).filter(|_| awaiting_time) {
+            if let Some(last) = messages.last_mut() {
+                last.time = cap[1].parse().ok();
+                last.unit = cap.get(2).map(|m| match m.as_str() {
+                    unit if unit.starts_with(char::is_numeric) => unit.replace(' ', ""),
+                    unit => format!("1{unit}"),
+                });
+                last.scope = Some(cap[3].to_owned());
+            }
+            awaiting_time = false;
+            continue;
+        }
+        awaiting_time = false;
+
+        if let Some(cap) = SEVERITY.captures(line) {
+            let severity = match &cap[1] {
+                "INFO" => Severity::Info,
+                "WARNING" => Severity::Warning,
+                "ERROR" => Severity::Error,
+                _ => Severity::Fatal,
+            };
+            let mut message = SimulationMessage::new(severity, &cap[4]);
+            message.file = cap.get(2).map(|m| m.as_str().to_owned());
+            message.line = cap.get(3).and_then(|m| m.as_str().parse().ok());
+            awaiting_time = message.file.is_some();
+            messages.push(message);
+        } else if let Some(cap) = FINISH.captures(line) {
+            let mut message =
+                SimulationMessage::new(Severity::Info, &format!("${} called", &cap[3]));
+            message.file = cap.get(1).map(|m| m.as_str().to_owned());
+            message.line = cap.get(2).and_then(|m| m.as_str().parse().ok());
+            message.time = cap[4].parse().ok();
+            message.unit = Some(cap[5].to_owned());
+            messages.push(message);
+        } else if let Some(cap) = DUMPER.captures(line) {
+            let severity = match &cap[2] {
+                "info" => Severity::Info,
+                "warning" => Severity::Warning,
+                _ => Severity::Error,
+            };
+            messages.push(SimulationMessage::new(
+                severity,
+                &format!("{} {}: {}", &cap[1], &cap[2], &cap[3]),
+            ));
+        }
+    }
+
+    messages
+}
+
+/// Returns the messages printed by a simulation run
+#[tauri::command]
+pub fn get_messages(run: u64, state: AppState<'_>) -> Result<Vec<SimulationMessage>, Error> {
+    state
+        .lock()
+        .unwrap()
+        .transcript(run)
+        .map(parse_messages)
+        .ok_or_else(|| Error::Other(format!("No simulation run with id {run}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::transcript::Stream;
+
+    #[test]
+    fn transcript_messages() {
+        let transcript = [
+            "VCD info: dumpfile dump.vcd opened for output.",
+            "VCD warning: $dumpvars: Unsupported argument type (vpiPort)",
+            "value = 3",
+            "ERROR: wrong result",
+            "WARNING: tb.v:18: x is undefined",
+            "       Time: 40 Scope: tb.check",
+            "FATAL: tb.v:25: overflow",
+            "       Time: 50 (ns) Scope: tb",
+            "tb.v:25: $finish called at 50 (1ns)",
+        ]
+        .map(|line| TranscriptLine {
+            stream: Stream::Stdout,
+            line: line.to_owned(),
+        });
+
+        let messages = parse_messages(&transcript);
+        assert_eq!(
+            messages,
+            vec![
+                SimulationMessage::new(
+                    Severity::Info,
+                    "VCD info: dumpfile dump.vcd opened for output."
+                ),
+                SimulationMessage::new(
+                    Severity::Warning,
+                    "VCD warning: $dumpvars: Unsupported argument type (vpiPort)"
+                ),
+                SimulationMessage::new(Severity::Error, "wrong result"),
+                SimulationMessage {
+                    time: Some(40),
+                    file: Some("tb.v".to_owned()),
+                    line: Some(18),
+                    scope: Some("tb.check".to_owned()),
+                    ..SimulationMessage::new(Severity::Warning, "x is undefined")
+                },
+                SimulationMessage {
+                    time: Some(50),
+                    unit: Some("1ns".to_owned()),
+                    file: Some("tb.v".to_owned()),
+                    line: Some(25),
+                    scope: Some("tb".to_owned()),
+                    ..SimulationMessage::new(Severity::Fatal, "overflow")
+                },
+                SimulationMessage {
+                    time: Some(50),
+                    unit: Some("1ns".to_owned()),
+                    file: Some("tb.v".to_owned()),
+                    line: Some(25),
+                    ..SimulationMessage::new(Severity::Info, "$finish called")
+                },
+            ]
+        );
+    }
+}
@@ -1,6 +1,11 @@
-mod iverilog;
-mod vcd;
-mod vvp;
+pub mod cache;
+pub mod iverilog;
+pub mod messages;
+pub mod process;
+pub mod top;
+pub mod transcript;
+pub mod vcd;
+pub mod vvp;
 
 pub use iverilog::compile;
 pub use vvp::simulate;
@@ -0,0 +1,83 @@
+//! Tracking of the `iverilog` and `vvp` processes, so that they can be cancelled.
+
+use std::{
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use serde::Serialize;
+
+use crate::{error::Error, state::AppState};
+
+/// External process started by a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningProcess {
+    pub pid: u32,
+    pub program: String,
+    /// Set when the process is killed on request, to tell it apart from a crash
+    #[serde(skip)]
+    pub cancelled: Arc<AtomicBool>,
+}
+
+/// Makes the process the leader of a new process group, so that its children can be killed
+/// along with it
+pub fn new_process_group(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Kills a process and all its children
+pub fn kill_tree(pid: u32) -> Result<(), Error> {
+    #[cfg(windows)]
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .status()?;
+    #[cfg(not(windows))]
+    let status = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{pid}")])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Other(format!("Could not kill process {pid}")))
+    }
+}
+
+impl RunningProcess {
+    pub fn cancel(&self) -> Result<(), Error> {
+        tracing::info!("Cancelling {} (pid {})", self.program, self.pid);
+        self.cancelled.store(true, Ordering::SeqCst);
+        kill_tree(self.pid)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Kills the processes of a run and of the runs it started, or of all the runs if `run` is not
+/// given
+#[tauri::command]
+pub fn cancel(run: Option<u64>, state: AppState<'_>) -> Result<(), Error> {
+    let state = state.lock().unwrap();
+    match run {
+        Some(run) => {
+            let processes = state.processes_of(run).collect::<Vec<_>>();
+            if processes.is_empty() {
+                return Err(Error::Other(format!("Run {run} is not running")));
+            }
+            processes.into_iter().try_for_each(RunningProcess::cancel)
+        }
+        None => state.processes().try_for_each(RunningProcess::cancel),
+    }
+}
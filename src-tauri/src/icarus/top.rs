@@ -0,0 +1,122 @@
+//! Detection of the top-level modules of a target, i.e. the modules which are never instantiated.
+
+use std::{fs, path::PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{error::Error, state::AppState, testing::DEFAULT_PATTERN, verilog};
+
+/// Module defined but never instantiated in the files of a target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootModule {
+    pub name: String,
+    pub file: PathBuf,
+    pub has_ports: bool,
+    pub calls_finish: bool,
+    /// Likelihood of the module being a testbench, from its ports, `$finish` calls and name
+    pub score: u32,
+}
+
+impl RootModule {
+    fn new(module: &verilog::Module, file: PathBuf) -> Self {
+        lazy_static! {
+            // Same names as the testbenches discovered by default
+            static ref TESTBENCH: Regex = Regex::new(DEFAULT_PATTERN).unwrap();
+        }
+
+        let has_ports = verilog::has_ports(&module.body);
+        let calls_finish = verilog::contains_identifier(&module.body, "$finish");
+        let testbench_name = TESTBENCH.is_match(&module.name);
+
+        Self {
+            name: module.name.clone(),
+            file,
+            has_ports,
+            calls_finish,
+            score: 2 * u32::from(!has_ports)
+                + 2 * u32::from(calls_finish)
+                + u32::from(testbench_name),
+        }
+    }
+}
+
+/// Lists the root modules defined in `files`, the most likely testbenches first
+pub fn root_modules(files: &[PathBuf]) -> Result<Vec<RootModule>, Error> {
+    let mut modules = vec![];
+    for file in files {
+        for module in verilog::modules(&fs::read_to_string(file)?) {
+            modules.push((module, file));
+        }
+    }
+
+    let definitions = modules.iter().map(|m| m.0.clone()).collect::<Vec<_>>();
+    let roots = verilog::root_modules(&definitions);
+    let mut roots = modules
+        .iter()
+        .filter(|(module, _)| roots.iter().any(|r| r.name == module.name))
+        .map(|(module, file)| RootModule::new(module, file.to_path_buf()))
+        .collect::<Vec<_>>();
+    roots.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    Ok(roots)
+}
+
+/// Lists the root modules of a target, the default one if `target` is not given
+#[tauri::command]
+pub fn top_modules(target: Option<String>, state: AppState<'_>) -> Result<Vec<RootModule>, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    root_modules(&project.target(target.as_deref())?.source_files(&project)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ranking() {
+        let directory = std::env::temp_dir().join("palusim_top_modules");
+        fs::create_dir_all(&directory).unwrap();
+        let files = [
+            (
+                "counter.v",
+                "module counter(input clk, output reg [3:0] q);\nendmodule\n\
+                 module debug(input clk);\nendmodule\n\
+                 module tbuf(input a);\nendmodule\n\
+                 module attest(input a);\nendmodule\n\
+                 module counter_test(input clk);\nendmodule\n",
+            ),
+            (
+                "tb.v",
+                "module tb_counter;\n  counter c(clk, q);\n  initial #100 $finish;\nendmodule\n",
+            ),
+        ]
+        .map(|(name, content)| {
+            let path = directory.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        });
+
+        let roots = root_modules(&files).unwrap();
+        assert_eq!(
+            roots
+                .iter()
+                .map(|r| (r.name.as_str(), r.score))
+                .collect::<Vec<_>>(),
+            vec![
+                ("tb_counter", 5),
+                ("counter_test", 1),
+                ("attest", 0),
+                ("debug", 0),
+                ("tbuf", 0)
+            ]
+        );
+        assert_eq!(roots[0].file, files[1]);
+    }
+}
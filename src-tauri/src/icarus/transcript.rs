@@ -0,0 +1,209 @@
+//! Output of the simulations, forwarded line by line to the frontend while they run.
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::process::{kill_tree, new_process_group, RunningProcess};
+use crate::{
+    error::Error,
+    state::{AppState, State},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptLine {
+    pub stream: Stream,
+    pub line: String,
+}
+
+/// Reason for which a process was killed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Cancelled,
+    TimeLimit,
+}
+
+#[derive(Debug)]
+pub struct ProcessOutput {
+    pub status: ExitStatus,
+    pub transcript: Vec<TranscriptLine>,
+    pub stop: Option<Stop>,
+}
+
+/// Payload of the output events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputEvent<'a> {
+    run: u64,
+    #[serde(flatten)]
+    line: &'a TranscriptLine,
+}
+
+/// Sends the lines read from `reader` until it is closed. Lines are decoded lossily, since
+/// `$display` can print arbitrary bytes.
+fn forward(
+    reader: impl Read + Send + 'static,
+    stream: Stream,
+    sender: Sender<TranscriptLine>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buffer = vec![];
+        while reader.read_until(b'\n', &mut buffer).is_ok_and(|n| n > 0) {
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\n', '\r'])
+                .to_owned();
+            buffer.clear();
+            if sender.send(TranscriptLine { stream, line }).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Runs `command` as the process of `run`, emitting each line of its output as an `event` tagged
+/// with `run`. The process is registered in the state while it runs so that it can be cancelled,
+/// the state must hence not be locked by the caller. It is killed after `time_limit`.
+pub fn run_streaming(
+    mut command: Command,
+    run: u64,
+    event: &str,
+    time_limit: Option<Duration>,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<ProcessOutput, Error> {
+    new_process_group(&mut command);
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let process = RunningProcess {
+        pid: child.id(),
+        program: Path::new(command.get_program())
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        cancelled: Default::default(),
+    };
+    state.lock().unwrap().add_process(run, process.clone());
+
+    let (sender, receiver) = mpsc::channel();
+    let mut readers = vec![];
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(forward(stdout, Stream::Stdout, sender.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(forward(stderr, Stream::Stderr, sender));
+    }
+
+    let mut deadline = time_limit.map(|limit| Instant::now() + limit);
+    let mut timed_out = false;
+    let mut transcript = vec![];
+    loop {
+        let line = match deadline {
+            Some(d) => match receiver.recv_timeout(d.saturating_duration_since(Instant::now())) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    tracing::info!("{} reached its time limit", process.program);
+                    if let Err(e) = kill_tree(process.pid) {
+                        tracing::error!("{e:?}");
+                    }
+                    timed_out = true;
+                    deadline = None;
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match receiver.recv() {
+                Ok(line) => line,
+                Err(_) => break,
+            },
+        };
+
+        if let Err(e) = app.emit_all(event, OutputEvent { run, line: &line }) {
+            tracing::warn!("Could not forward process output: {e:?}");
+        }
+        transcript.push(line);
+    }
+
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = child.wait();
+    state.lock().unwrap().remove_process(run);
+
+    Ok(ProcessOutput {
+        status: status?,
+        transcript,
+        stop: if process.is_cancelled() {
+            Some(Stop::Cancelled)
+        } else if timed_out {
+            Some(Stop::TimeLimit)
+        } else {
+            None
+        },
+    })
+}
+
+/// Returns the output of a simulation run
+#[tauri::command]
+pub fn get_transcript(run: u64, state: AppState<'_>) -> Result<Vec<TranscriptLine>, Error> {
+    state
+        .lock()
+        .unwrap()
+        .transcript(run)
+        .map(<[TranscriptLine]>::to_vec)
+        .ok_or_else(|| Error::Other(format!("No simulation run with id {run}")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn forwarded_lines() {
+        let (sender, receiver) = mpsc::channel();
+        forward(
+            Cursor::new(
+                b"VCD info: dumpfile test.vcd opened for output.\r\nvalue = \xff\nlast".to_vec(),
+            ),
+            Stream::Stderr,
+            sender,
+        )
+        .join()
+        .unwrap();
+
+        let lines = receiver.into_iter().map(|l| l.line).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "VCD info: dumpfile test.vcd opened for output.",
+                "value = \u{fffd}",
+                "last"
+            ]
+        );
+    }
+}
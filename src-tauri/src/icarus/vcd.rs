@@ -4,7 +4,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::Serialize;
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VCDFile {
     variables: VariableScope,
@@ -14,7 +14,7 @@ pub struct VCDFile {
     timeline: HashMap<char, HashMap<u32, String>>,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct VariableScope {
     name: Option<String>,
@@ -23,13 +23,159 @@ struct VariableScope {
     scopes: Vec<VariableScope>,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct Variable {
-    ty: String,
-    size: u32,
-    identifier: char,
-    reference: String,
+pub struct Variable {
+    pub ty: String,
+    pub size: u32,
+    pub identifier: char,
+    pub reference: String,
+}
+
+impl VCDFile {
+    /// Finds a variable from its dotted path (e.g. `test.c1.value`)
+    pub fn find_variable(&self, path: &str) -> Option<&Variable> {
+        let mut parts = path.split('.').collect::<Vec<_>>();
+        let reference = parts.pop()?;
+
+        let mut scope = &self.variables;
+        for part in parts {
+            scope = scope
+                .scopes
+                .iter()
+                .find(|s| s.name.as_deref() == Some(part))?;
+        }
+
+        scope.variables.iter().find(|v| v.reference == reference)
+    }
+
+    /// Lists all the variables of the dump, with their dotted path
+    pub fn variables(&self) -> Vec<(String, &Variable)> {
+        fn collect<'a>(
+            scope: &'a VariableScope,
+            prefix: &str,
+            acc: &mut Vec<(String, &'a Variable)>,
+        ) {
+            let prefix = match &scope.name {
+                Some(name) if prefix.is_empty() => name.clone(),
+                Some(name) => format!("{prefix}.{name}"),
+                None => prefix.to_owned(),
+            };
+            for variable in &scope.variables {
+                if prefix.is_empty() {
+                    acc.push((variable.reference.clone(), variable));
+                } else {
+                    acc.push((format!("{prefix}.{}", variable.reference), variable));
+                }
+            }
+            for child in &scope.scopes {
+                collect(child, &prefix, acc);
+            }
+        }
+
+        let mut acc = vec![];
+        collect(&self.variables, "", &mut acc);
+        acc
+    }
+
+    /// Returns the value changes of a variable, sorted by time
+    pub fn changes(&self, identifier: char) -> Vec<(u32, &str)> {
+        let mut changes = self
+            .timeline
+            .get(&identifier)
+            .map(|t| t.iter().map(|(k, v)| (*k, v.as_str())).collect::<Vec<_>>())
+            .unwrap_or_default();
+        changes.sort_by_key(|c| c.0);
+        changes
+    }
+
+    /// Last timestamp of the dump
+    pub fn end_time(&self) -> u32 {
+        self.timeline
+            .values()
+            .flat_map(|t| t.keys())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Duration of a tick of the dump, in seconds
+    pub fn timescale_seconds(&self) -> f64 {
+        let unit = match self.timescale.1.as_str() {
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" => 1e-6,
+            "ns" => 1e-9,
+            "ps" => 1e-12,
+            _ => 1e-15,
+        };
+        self.timescale.0 as f64 * unit
+    }
+
+    /// Merges dumps into a single timeline, each of them being placed under a root scope with the
+    /// given name. Times are converted to the finest timescale of the dumps, after being shifted by
+    /// the given offset (in the time unit of their dump). Changes shifted before the start of the
+    /// timeline are applied at time 0.
+    pub fn merge(dumps: &[(&str, &VCDFile, i64)]) -> Result<VCDFile, String> {
+        let finest = dumps
+            .iter()
+            .map(|d| d.1)
+            .min_by(|a, b| a.timescale_seconds().total_cmp(&b.timescale_seconds()))
+            .ok_or("No dump to merge".to_owned())?;
+
+        let mut merged = VCDFile {
+            timescale: finest.timescale.clone(),
+            version: "PaluSim merge".to_owned(),
+            date: finest.date.clone(),
+            ..Default::default()
+        };
+        // Identifiers are reassigned so that the variables of different dumps do not collide
+        let mut next_identifier = '!' as u32;
+
+        for (scope, vcd, offset) in dumps {
+            let factor = (vcd.timescale_seconds() / finest.timescale_seconds()).round() as i64;
+            let mut identifiers = HashMap::new();
+            let used = vcd.variables().into_iter().map(|(_, v)| v.identifier);
+            for identifier in used.chain(vcd.timeline.keys().copied()) {
+                identifiers.entry(identifier).or_insert_with(|| loop {
+                    let c = char::from_u32(next_identifier);
+                    next_identifier += 1;
+                    if let Some(c) = c {
+                        break c;
+                    }
+                });
+            }
+
+            let mut root = vcd.variables.clone();
+            root.name = Some(scope.to_string());
+            root.ty = Some("module".to_owned());
+            root.rename_identifiers(&identifiers);
+            merged.variables.scopes.push(root);
+
+            for identifier in vcd.timeline.keys() {
+                let changes = merged.timeline.entry(identifiers[identifier]).or_default();
+                for (time, value) in vcd.changes(*identifier) {
+                    let time = ((time as i64 + offset) * factor).max(0);
+                    let time = u32::try_from(time)
+                        .map_err(|_| format!("Time {time} of {scope} is out of range"))?;
+                    changes.insert(time, value.to_owned());
+                }
+            }
+        }
+
+        Ok(merged)
+    }
+}
+
+impl VariableScope {
+    fn rename_identifiers(&mut self, identifiers: &HashMap<char, char>) {
+        for variable in &mut self.variables {
+            variable.identifier = identifiers[&variable.identifier];
+        }
+        for scope in &mut self.scopes {
+            scope.rename_identifiers(identifiers);
+        }
+    }
 }
 
 /// Parses the $timescale instruction. Returns:
@@ -364,6 +510,21 @@ impl FromStr for VCDFile {
     }
 }
 
+/// Parses a dump with a timescale of 1ns, declaring the `variables` (e.g. `wire 1 ! clk`) in the
+/// module `scope` and changing them as in `changes`
+#[cfg(test)]
+pub fn test_dump(scope: &str, variables: &[&str], changes: &str) -> VCDFile {
+    let variables = variables
+        .iter()
+        .map(|v| format!("$var {v} $end\n"))
+        .collect::<String>();
+    VCDFile::from_str(&format!(
+        "$date today $end\n$version test $end\n$timescale 1ns $end\n$scope module {scope} $end\n\
+         {variables}$upscope $end\n$enddefinitions $end\n{changes}"
+    ))
+    .unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -603,4 +764,30 @@ b101010101010110101010101010101 !
         assert_eq!(timeline.get(&'%').map(|t| t.len()), Some(2));
         assert_eq!(timeline.get(&'^').map(|t| t.len()), Some(1));
     }
+
+    #[test]
+    fn merge() {
+        let first = test_dump("tb", &["wire 1 ! clk"], "#0\n0!\n#10\n1!\n#20\n0!\n");
+        let mut second = test_dump(
+            "tb",
+            &["wire 1 ! clk", "wire 1 \" q"],
+            "#0\n1!\n0\"\n#50\n0!\n1\"\n",
+        );
+        // The times of the second dump are in units of 100ps
+        second.timescale = (100, "ps".to_owned());
+
+        let merged = VCDFile::merge(&[("a", &first, 0), ("b", &second, -20)]).unwrap();
+
+        assert_eq!(merged.timescale, (100, "ps".to_owned()));
+        let clk_a = merged.find_variable("a.tb.clk").unwrap().identifier;
+        let clk_b = merged.find_variable("b.tb.clk").unwrap().identifier;
+        let q = merged.find_variable("b.tb.q").unwrap().identifier;
+        assert_ne!(clk_a, clk_b);
+        assert_eq!(
+            merged.changes(clk_a),
+            vec![(0, "0"), (100, "1"), (200, "0")]
+        );
+        assert_eq!(merged.changes(clk_b), vec![(0, "1"), (30, "0")]);
+        assert_eq!(merged.changes(q), vec![(0, "0"), (30, "1")]);
+    }
 }
@@ -3,70 +3,351 @@ use std::{
     path::{Path, PathBuf},
     process::Command,
     str::FromStr,
+    sync::Mutex,
+    time::Duration,
 };
 
 use regex::Regex;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
 
-use super::vcd::VCDFile;
-use crate::{consts::VVP_EXE, error::Error, state::AppState};
+use super::{
+    cache,
+    iverilog::TIME_LIMIT_MESSAGE,
+    messages::{parse_messages, SimulationMessage},
+    transcript::{run_streaming, ProcessOutput, Stop, Stream, TranscriptLine},
+    vcd::VCDFile,
+};
+use crate::{
+    consts::VVP_EXE,
+    error::Error,
+    history,
+    jobs::{self, JobKind},
+    project::{Project, Target},
+    state::{AppState, State},
+    waves::session::WaveSession,
+};
 
 lazy_static::lazy_static! {
-    static ref VCD_FILE_REGEX: Regex = Regex::new("^VCD info: dumpfile (.*) opened for output\\.").unwrap();
+    static ref DUMP_FILE_REGEX: Regex = Regex::new("^(VCD|FST|LXT2?) info: dumpfile (.*) opened for output\\.").unwrap();
+}
+
+/// Options a simulation was run with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationRecord {
+    pub run: u64,
+    /// Target simulated, with its plusargs and runtime options
+    pub target: Target,
+    pub seed: Option<u32>,
+    /// Working directory of `vvp`
+    pub directory: PathBuf,
+    /// Arguments of `vvp`
+    pub arguments: Vec<String>,
+}
+
+/// Payload of the `simulation-messages` events, sent once a simulation is finished
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MessagesEvent {
+    run: u64,
+    messages: Vec<SimulationMessage>,
+}
+
+/// Starts simulating the last compilation of a target in the background, the default one if
+/// `target` is not given, returning the id of the job. The seed of the target is used if `seed` is
+/// not given.
+#[tauri::command]
+pub fn simulate(
+    target: Option<String>,
+    seed: Option<u32>,
+    state: AppState<'_>,
+    app: AppHandle,
+) -> Result<u64, Error> {
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    let target = project.target(target.as_deref())?;
+    Ok(jobs::spawn(
+        JobKind::Simulation,
+        &mut state,
+        &app,
+        move |run, state, app| {
+            let seed = seed.or_else(|| target.choose_seed());
+            let directory = project.output_directory()?;
+            simulate_project(&project, &target, &directory, seed, run, state, app)
+        },
+    ))
 }
 
+/// Starts simulating again in the background the executable of the run `run`, with the same
+/// options and seed, returning the id of the job
 #[tauri::command]
-pub fn simulate(state: AppState<'_>, app: AppHandle) -> Result<Vec<VCDFile>, Error> {
-    if let Some(project) = state.lock().unwrap().project() {
-        run_simulation(
-            &project.output_directory()?.join("a.out"),
-            &project.output_directory()?,
-            app,
-        )
+pub fn rerun_simulation(run: u64, state: AppState<'_>, app: AppHandle) -> Result<u64, Error> {
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    let record = state
+        .record(run)
+        .cloned()
+        .ok_or_else(|| Error::Other(format!("No simulation run with id {run}")))?;
+    Ok(jobs::spawn(
+        JobKind::Simulation,
+        &mut state,
+        &app,
+        move |run, state, app| {
+            simulate_project(
+                &project,
+                &record.target,
+                &record.directory,
+                record.seed,
+                run,
+                state,
+                app,
+            )
+        },
+    ))
+}
+
+/// Simulates the last compilation of `target` in `directory` as the run `run`, and loads the dumps
+/// it produced. The state must not be locked by the caller, so that the simulation can be
+/// cancelled.
+pub fn simulate_project(
+    project: &Project,
+    target: &Target,
+    directory: &Path,
+    seed: Option<u32>,
+    run: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<Vec<VCDFile>, Error> {
+    app.emit_all("simulation-started", run)?;
+    let record = simulation_record(target, seed, directory, run);
+    let cached = if project.cache.simulation {
+        cache::cached_simulation(project, &record)
     } else {
-        Err(Error::NoProject)
+        None
+    };
+    let transcript = match cached {
+        Some(transcript) => {
+            tracing::info!("Reusing the previous simulation of {}", target.name);
+            {
+                let mut state = state.lock().unwrap();
+                state.set_record(run, record.clone());
+                state.set_transcript(run, transcript.clone());
+            }
+            emit_messages(run, &transcript, app)?;
+            // Only the successful simulations are cached
+            if let Err(e) = history::save(project, &record, &transcript, Some(0), false) {
+                tracing::warn!("Could not save run {run} in the history: {e:?}");
+            }
+            transcript
+        }
+        None => {
+            jobs::progress(run, "Simulating", state, app);
+            let transcript = checked_simulation(project, target, directory, seed, run, state, app)?;
+            if project.cache.simulation {
+                if let Err(e) = cache::store_simulation(project, &record, &transcript) {
+                    tracing::warn!("Could not cache run {run}: {e:?}");
+                }
+            }
+            transcript
+        }
+    };
+    jobs::progress(run, "Reading dumps", state, app);
+    let waves = read_dumps(&transcript, directory)?;
+
+    // Restores the traces displayed before the simulation
+    let mut session = WaveSession::load(project)?;
+    session.retain_existing(&waves);
+    app.emit_all("session", session)?;
+
+    *state.lock().unwrap().waves_mut() = waves.clone();
+    Ok(waves)
+}
+
+fn emit_messages(run: u64, transcript: &[TranscriptLine], app: &AppHandle) -> Result<(), Error> {
+    app.emit_all(
+        "simulation-messages",
+        MessagesEvent {
+            run,
+            messages: parse_messages(transcript),
+        },
+    )?;
+    Ok(())
+}
+
+/// Runs the simulation, saves it in the history and returns its transcript if it finished
+/// successfully
+fn checked_simulation(
+    project: &Project,
+    target: &Target,
+    directory: &Path,
+    seed: Option<u32>,
+    run: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<Vec<TranscriptLine>, Error> {
+    let output = run_simulation(
+        target,
+        seed,
+        directory,
+        run,
+        project.limits.wall_clock.map(Duration::from_secs),
+        state,
+        app,
+    )?;
+    state
+        .lock()
+        .unwrap()
+        .set_transcript(run, output.transcript.clone());
+    emit_messages(run, &output.transcript, app)?;
+
+    tracing::info!("vvp exited with {:?}", output.status.code());
+    if output.stop != Some(Stop::Cancelled) {
+        let record = state.lock().unwrap().record(run).cloned();
+        if let Some(record) = record {
+            let timed_out = output.stop == Some(Stop::TimeLimit);
+            let saved = history::save(
+                project,
+                &record,
+                &output.transcript,
+                output.status.code(),
+                timed_out,
+            );
+            if let Err(e) = saved {
+                tracing::warn!("Could not save run {run} in the history: {e:?}");
+            }
+        }
+    }
+    match output.stop {
+        Some(Stop::Cancelled) => return Err(Error::Cancelled),
+        Some(Stop::TimeLimit) => {
+            return Err(Error::TimeLimit(format!(
+                "Simulation stopped after {} s",
+                project.limits.wall_clock.unwrap_or_default()
+            )))
+        }
+        None => {}
+    }
+    if let Some(limit) = &project.limits.simulated_time {
+        if output
+            .transcript
+            .iter()
+            .any(|line| line.line == TIME_LIMIT_MESSAGE)
+        {
+            return Err(Error::TimeLimit(format!(
+                "Simulation finished at {limit} of simulated time"
+            )));
+        }
+    }
+    if output.status.code().is_some_and(|v| v != 0) {
+        return Err(Error::Other(format!(
+            "Could not simulate with vvp (exit code {:?})",
+            output.status.code(),
+        )));
     }
+    Ok(output.transcript)
 }
 
+/// Options of the simulation `run` of `target` with `seed`
+fn simulation_record(
+    target: &Target,
+    seed: Option<u32>,
+    output_directory: &Path,
+    run: u64,
+) -> SimulationRecord {
+    let target = target.with_seed(seed);
+    SimulationRecord {
+        run,
+        seed,
+        directory: output_directory.to_path_buf(),
+        arguments: target.simulator_args(&target.executable(output_directory)),
+        target,
+    }
+}
+
+/// Runs `vvp` on the executable of `target` with `seed`, streaming its output as the events of the
+/// simulation `run`, and records the options it was run with
 pub fn run_simulation(
-    executable: &Path,
+    target: &Target,
+    seed: Option<u32>,
     output_directory: &Path,
-    app: AppHandle,
-) -> Result<Vec<VCDFile>, Error> {
+    run: u64,
+    time_limit: Option<Duration>,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<ProcessOutput, Error> {
+    let record = simulation_record(target, seed, output_directory, run);
+    let arguments = record.arguments.clone();
     tracing::info!("Starting simulation");
-    tracing::debug!("{output_directory:?}: {VVP_EXE} {executable:?}");
+    tracing::debug!("{output_directory:?}: {VVP_EXE} {arguments:?}");
+
+    state.lock().unwrap().set_record(run, record);
 
-    let output = Command::new(
+    let mut command = Command::new(
         app.path_resolver()
             .resolve_resource(VVP_EXE)
             .expect("Missing vvp executable"),
-    )
-    .args([executable])
-    .current_dir(PathBuf::from(output_directory))
-    .output()?;
+    );
+    command
+        .args(&arguments)
+        .current_dir(PathBuf::from(output_directory));
 
-    tracing::info!("vvp exited with {:?}", output.status.code());
-    if output.status.code().is_some_and(|v| v != 0) {
-        return Err(Error::Other(format!(
-            "Could not simulate with vvp (exit code {:?})",
-            output.status.code(),
-        )));
+    run_streaming(command, run, "simulation-output", time_limit, state, app)
+}
+
+/// Returns the options a simulation run was started with
+#[tauri::command]
+pub fn get_run_record(run: u64, state: AppState<'_>) -> Result<SimulationRecord, Error> {
+    state
+        .lock()
+        .unwrap()
+        .record(run)
+        .cloned()
+        .ok_or_else(|| Error::Other(format!("No simulation run with id {run}")))
+}
+
+/// Lists the dumps opened by a simulation, as reported in its transcript, with their format
+fn dumps<'a>(
+    transcript: &'a [TranscriptLine],
+    output_directory: &'a Path,
+) -> impl Iterator<Item = (String, PathBuf)> + 'a {
+    transcript
+        .iter()
+        .filter(|line| line.stream == Stream::Stdout)
+        .filter_map(|line| DUMP_FILE_REGEX.captures(&line.line))
+        .map(|captures| (captures[1].to_owned(), output_directory.join(&captures[2])))
+}
+
+/// Lists the dumps opened by a simulation, as reported in its transcript
+pub fn dump_paths(transcript: &[TranscriptLine], output_directory: &Path) -> Vec<PathBuf> {
+    dumps(transcript, output_directory)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Reads the dumps opened by a simulation, as reported in its transcript. Only the VCD dumps can
+/// be displayed, the simulation fails if it only wrote dumps in other formats.
+pub fn read_dumps(
+    transcript: &[TranscriptLine],
+    output_directory: &Path,
+) -> Result<Vec<VCDFile>, Error> {
+    let (vcd, others): (Vec<_>, Vec<_>) =
+        dumps(transcript, output_directory).partition(|(format, _)| format == "VCD");
+    if let Some((format, path)) = others.first() {
+        let message = format!(
+            "The {format} dump {} cannot be displayed, set the dump format of the target to vcd",
+            path.display()
+        );
+        if vcd.is_empty() {
+            return Err(Error::Other(message));
+        }
+        tracing::warn!("{message}");
     }
 
-    String::from_utf8_lossy(&output.stdout)
-        .split('\n')
-        .filter_map(|line| {
-            VCD_FILE_REGEX
-                .captures(line)
-                .map(|captures| -> Result<_, Error> {
-                    let content =
-                        fs::read_to_string(PathBuf::from(output_directory).join(&captures[1]))?;
-                    VCDFile::from_str(&content)
-                        .map_err(|_| Error::Other("Could not parse VCD file".to_owned()))
-                })
-        })
-        .try_fold(vec![], |mut vec, value| {
-            vec.push(value?);
-            Ok(vec)
+    vcd.into_iter()
+        .map(|(_, path)| {
+            VCDFile::from_str(&fs::read_to_string(path)?)
+                .map_err(|_| Error::Other("Could not parse VCD file".to_owned()))
         })
+        .collect()
 }
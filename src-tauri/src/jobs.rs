@@ -0,0 +1,192 @@
+//! Background jobs, so that long commands such as compilations and simulations return
+//! immediately instead of blocking the other commands.
+//!
+//! A job is identified by the id of its run, so that it can be cancelled with
+//! [`cancel`](crate::icarus::process::cancel). It reports its progress with `job-progress` events
+//! and its status with `job-status` events, the latter carrying the result once it is finished.
+
+use std::{
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    error::Error,
+    state::{AppState, State},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobKind {
+    Compilation,
+    Simulation,
+    Tests,
+    Sweep,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Cancelled,
+    Failed {
+        /// Serialized [`Error`], as it would have been returned by a command
+        error: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: u64,
+    pub kind: JobKind,
+    /// Start time, in milliseconds since the UNIX epoch
+    pub started: u64,
+    /// Duration in milliseconds, once finished
+    pub duration: Option<u64>,
+    /// Last step reported by the job
+    pub stage: Option<String>,
+    #[serde(flatten)]
+    pub status: JobStatus,
+}
+
+/// Payload of the `job-status` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusEvent {
+    #[serde(flatten)]
+    job: Job,
+    /// Value returned by the job, if it succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+}
+
+/// Payload of the `job-progress` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent<'a> {
+    job: u64,
+    stage: &'a str,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn emit_status(app: &AppHandle, job: Job, result: Option<serde_json::Value>) {
+    if let Err(e) = app.emit_all("job-status", StatusEvent { job, result }) {
+        tracing::warn!("Could not send job status: {e:?}");
+    }
+}
+
+/// Runs `task` in a new thread, returning the id of its job. The state is passed to the task
+/// unlocked, it must only be locked for short periods.
+pub fn spawn<T, F>(kind: JobKind, state: &mut State, app: &AppHandle, task: F) -> u64
+where
+    T: Serialize,
+    F: FnOnce(u64, &Mutex<State>, &AppHandle) -> Result<T, Error> + Send + 'static,
+{
+    let job = Job {
+        id: state.new_run(),
+        kind,
+        started: now(),
+        duration: None,
+        stage: None,
+        status: JobStatus::Running,
+    };
+    let id = job.id;
+    state.add_job(job.clone());
+    emit_status(app, job, None);
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let state = app.state::<Mutex<State>>();
+        let result = task(id, state.inner(), &app).and_then(|v| {
+            serde_json::to_value(v).map_err(|e| Error::Other(format!("Invalid job result: {e}")))
+        });
+
+        let (status, result) = match result {
+            Ok(value) => (JobStatus::Succeeded, Some(value)),
+            Err(Error::Cancelled) => (JobStatus::Cancelled, None),
+            Err(e) => {
+                tracing::error!("Job {id} failed: {e:?}");
+                let error = serde_json::to_value(&e).unwrap_or_default();
+                (JobStatus::Failed { error }, None)
+            }
+        };
+
+        let job = {
+            let mut state = state.lock().unwrap();
+            // The runs of the job are finished, their processes can no longer be cancelled
+            state.remove_children(id);
+            state.job_mut(id).map(|job| {
+                job.duration = Some(now().saturating_sub(job.started));
+                job.status = status;
+                job.clone()
+            })
+        };
+        if let Some(job) = job {
+            emit_status(&app, job, result);
+        }
+    });
+
+    id
+}
+
+/// Records the step the job `id` is at and reports it to the frontend
+pub fn progress(id: u64, stage: &str, state: &Mutex<State>, app: &AppHandle) {
+    // Runs started by another job, such as the tests of a test run, have no job of their own
+    match state.lock().unwrap().job_mut(id) {
+        Some(job) => job.stage = Some(stage.to_owned()),
+        None => return,
+    }
+    if let Err(e) = app.emit_all("job-progress", ProgressEvent { job: id, stage }) {
+        tracing::warn!("Could not send job progress: {e:?}");
+    }
+}
+
+/// Returns the running and finished jobs, oldest first
+#[tauri::command]
+pub fn list_jobs(state: AppState<'_>) -> Vec<Job> {
+    state.lock().unwrap().jobs().cloned().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn job_serialization() {
+        let job = Job {
+            id: 3,
+            kind: JobKind::Simulation,
+            started: 1000,
+            duration: Some(20),
+            stage: Some("Reading dumps".to_owned()),
+            status: JobStatus::Failed {
+                error: serde_json::to_value(Error::NoProject).unwrap(),
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(StatusEvent { job, result: None }).unwrap(),
+            serde_json::json!({
+                "id": 3,
+                "kind": "simulation",
+                "started": 1000,
+                "duration": 20,
+                "stage": "Reading dumps",
+                "status": "failed",
+                "error": "NoProject"
+            })
+        );
+    }
+}
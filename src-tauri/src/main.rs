@@ -10,17 +10,48 @@ use tauri::Manager;
 use tauri_plugin_log::fern::colors::ColoredLevelConfig;
 
 use crate::{
-    icarus::{compile, simulate},
+    assertions::{check_assertion, check_assertions},
+    decoders::{decode, decode_project},
+    history::{compare_runs, list_runs, open_run, prune_runs},
+    icarus::{
+        compile,
+        messages::get_messages,
+        process::cancel,
+        simulate,
+        top::top_modules,
+        transcript::get_transcript,
+        vvp::{get_run_record, rerun_simulation},
+    },
+    jobs::list_jobs,
     project::{get_project_state, read_project_tree, set_project_state},
+    testing::{discover_tests, report::write_test_report, run_seeds, run_tests, sweep::run_sweep},
+    waves::{
+        analog::analog_envelope,
+        export::export_waves,
+        format::format_signal,
+        merge::merge_dumps,
+        session::{export_gtkw, get_session, import_gtkw, set_session, split_bus},
+        timing::timing_checks,
+        translation::get_translations,
+        wavedrom::{read_wavedrom, wavedrom_from_vcd, wavedrom_stimulus, write_wavedrom},
+    },
 };
 
+pub mod assertions;
+pub mod cli;
 pub mod config;
 pub mod consts;
+pub mod decoders;
 pub mod error;
+pub mod history;
 pub mod icarus;
+pub mod jobs;
 pub mod project;
 pub mod state;
+pub mod testing;
 pub mod util;
+pub mod verilog;
+pub mod waves;
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
@@ -29,6 +60,11 @@ fn greet(name: &str) -> String {
 }
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::default()
@@ -42,7 +78,42 @@ fn main() {
             simulate,
             read_project_tree,
             get_project_state,
-            set_project_state
+            set_project_state,
+            decode,
+            decode_project,
+            format_signal,
+            get_translations,
+            analog_envelope,
+            get_session,
+            set_session,
+            import_gtkw,
+            export_gtkw,
+            split_bus,
+            export_waves,
+            wavedrom_from_vcd,
+            read_wavedrom,
+            wavedrom_stimulus,
+            write_wavedrom,
+            merge_dumps,
+            check_assertion,
+            check_assertions,
+            timing_checks,
+            get_transcript,
+            cancel,
+            list_jobs,
+            get_messages,
+            top_modules,
+            discover_tests,
+            run_tests,
+            write_test_report,
+            run_sweep,
+            get_run_record,
+            rerun_simulation,
+            run_seeds,
+            list_runs,
+            open_run,
+            compare_runs,
+            prune_runs
         ])
         .manage(Mutex::new(state::State::new(None)))
         .setup(|app| {
@@ -3,34 +3,171 @@ Project state
  */
 
 use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
     ffi::{OsStr, OsString},
     fs::{self, DirEntry},
-    path::PathBuf,
+    hash::BuildHasher,
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 use globset::GlobSet;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 
-use crate::{error::Error, state::AppState, util::build_glob_matcher};
+use crate::{
+    assertions::Assertion,
+    config::PROJECT_FILE,
+    decoders::DecoderInstance,
+    error::Error,
+    history::HistoryConfig,
+    icarus::cache::CacheConfig,
+    state::AppState,
+    testing::{sweep::Sweep, TestConfig},
+    util::build_glob_matcher,
+};
 
 lazy_static! {
     static ref HIDDEN_FILES: Regex = Regex::new("out(/.*)?").unwrap();
 }
 
-#[derive(Debug, Serialize, Clone)]
+/// Project state. Everything but the name and directory can be configured in the project file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Project {
     /// Project name
+    #[serde(default)]
     pub name: String,
     /// Directory to compile
+    #[serde(skip_deserializing)]
     pub project_directory: PathBuf,
     /// Files/Patterns to include in compilation
+    #[serde(default = "default_included_files")]
     pub included_files: Vec<String>,
     /// Files/Patterns to exclude from compilation
+    #[serde(default)]
     pub excluded_files: Vec<String>,
+    /// Decoders to run on the simulation dumps
+    #[serde(default)]
+    pub decoders: Vec<DecoderInstance>,
+    /// GTKWave translate filter files, indexed by the path of the translated signal
+    #[serde(default)]
+    pub translations: HashMap<String, PathBuf>,
+    /// Temporal properties checked on the simulation dumps
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Limits after which simulations are stopped
+    #[serde(default)]
+    pub limits: Limits,
+    /// Testbenches that can be compiled and simulated, the first one being the default
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    /// Discovery and pass/fail criteria of the tests
+    #[serde(default)]
+    pub tests: TestConfig,
+    /// Testbenches run for several values of their parameters, macros or plusargs
+    #[serde(default)]
+    pub sweeps: Vec<Sweep>,
+    /// Runs kept in the history of the simulations
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Compilations and simulations skipped when their inputs did not change
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+/// Testbench compiled into its own executable
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub name: String,
+    /// Top-level module, passed to `iverilog -s`. Every root module is elaborated by default.
+    #[serde(default)]
+    pub top: Option<String>,
+    /// Files/Patterns to include in compilation, instead of the ones of the project
+    #[serde(default)]
+    pub included_files: Option<Vec<String>>,
+    /// Files/Patterns to exclude from compilation, in addition to the ones of the project
+    #[serde(default)]
+    pub excluded_files: Vec<String>,
+    /// Name of the executable in the output directory, `<name>.out` by default
+    #[serde(default)]
+    pub executable: Option<String>,
+    /// Values of the parameters of the top-level module, passed to `iverilog -P`
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    /// Macros defined for the compilation, passed to `iverilog -D`
+    #[serde(default)]
+    pub defines: BTreeMap<String, Option<String>>,
+    /// Plusargs passed to `vvp`, read with `$test$plusargs` and `$value$plusargs`
+    #[serde(default)]
+    pub plusargs: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    pub runtime: RuntimeOptions,
+    /// Seed of the random functions passed to the simulations, none by default
+    #[serde(default)]
+    pub seed: Option<Seed>,
+    /// Name of the plusarg passing the seed, `seed` by default
+    #[serde(default)]
+    pub seed_plusarg: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Seed {
+    /// New seed for each simulation
+    Random,
+    Fixed(u32),
+}
+
+/// Generates a seed for the random functions of the simulations
+pub fn random_seed() -> u32 {
+    RandomState::new().hash_one(SystemTime::now()) as u32
+}
+
+/// Options of `vvp`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeOptions {
+    /// Format of the dumps, the one selected by the testbench by default
+    #[serde(default)]
+    pub dump_format: Option<DumpFormat>,
+    /// Makes `$stop` and interruptions finish the simulation instead of waiting for commands
+    #[serde(default)]
+    pub non_interactive: bool,
+    /// Directories searched for VPI modules
+    #[serde(default)]
+    pub vpi_paths: Vec<PathBuf>,
+    /// VPI modules to load
+    #[serde(default)]
+    pub vpi_modules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DumpFormat {
+    Vcd,
+    Fst,
+    Lxt2,
+    /// Disables the dumps
+    None,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    /// Duration of the simulations, in seconds
+    #[serde(default)]
+    pub wall_clock: Option<u64>,
+    /// Simulated time at which the simulations are finished, e.g. `10ms`
+    #[serde(default)]
+    pub simulated_time: Option<String>,
+}
+
+fn default_included_files() -> Vec<String> {
+    vec!["**/*.v".to_owned(), "**/*.verilog".to_owned()]
 }
 
 #[derive(Debug, Serialize)]
@@ -70,7 +207,7 @@ pub fn set_project_state(
     state: AppState<'_>,
     handle: AppHandle,
 ) -> Result<(), Error> {
-    let project = Project::from_dir(PathBuf::from(project_path));
+    let project = Project::from_dir(PathBuf::from(project_path))?;
     *state.lock().unwrap().project_mut() = Some(project);
     handle.emit_all("reload", "")?;
     Ok(())
@@ -78,20 +215,43 @@ pub fn set_project_state(
 
 impl Project {
     pub fn from_current_dir() -> Result<Self, Error> {
-        Ok(Self::from_dir(std::env::current_dir()?))
+        Self::from_dir(std::env::current_dir()?)
     }
 
-    pub fn from_dir(path: PathBuf) -> Self {
-        Project {
-            name: PathBuf::from(&path)
+    /// Opens the project in `path`, reading its project file if there is one
+    pub fn from_dir(path: PathBuf) -> Result<Self, Error> {
+        let project_file = path.join(PROJECT_FILE);
+        let mut project = if project_file.is_file() {
+            serde_json::from_str(&fs::read_to_string(&project_file)?).map_err(|e| {
+                Error::Other(format!("Could not parse {}: {e}", project_file.display()))
+            })?
+        } else {
+            Project {
+                name: String::new(),
+                project_directory: PathBuf::new(),
+                excluded_files: vec![],
+                included_files: default_included_files(),
+                decoders: vec![],
+                translations: HashMap::new(),
+                assertions: vec![],
+                limits: Limits::default(),
+                targets: vec![],
+                tests: TestConfig::default(),
+                sweeps: vec![],
+                history: HistoryConfig::default(),
+                cache: CacheConfig::default(),
+            }
+        };
+
+        if project.name.is_empty() {
+            project.name = path
                 .file_name()
                 .unwrap_or_default()
                 .to_string_lossy()
-                .to_string(),
-            project_directory: path,
-            excluded_files: vec![],
-            included_files: vec!["**/*.v".to_owned(), "**/*.verilog".to_owned()],
+                .to_string();
         }
+        project.project_directory = path;
+        Ok(project)
     }
 
     pub fn read_project_tree(&self, apply_filters: bool) -> Result<ProjectEntry, Error> {
@@ -162,6 +322,41 @@ impl Project {
         })
     }
 
+    /// Lists the files to compile, after applying the inclusion and exclusion patterns
+    pub fn source_files(&self) -> Result<Vec<PathBuf>, Error> {
+        fn extract_pathes(entries: Vec<ProjectEntry>, acc: &mut Vec<PathBuf>) {
+            for e in entries {
+                if e.children.is_empty() && !e.path.is_dir() {
+                    acc.push(e.path)
+                } else {
+                    extract_pathes(e.children, acc)
+                }
+            }
+        }
+
+        let mut files = vec![];
+        extract_pathes(self.read_project_tree(true)?.children, &mut files);
+        Ok(files)
+    }
+
+    /// Returns the target called `name`, or the default one. Projects without targets have a
+    /// default target compiling all their files into `a.out`.
+    pub fn target(&self, name: Option<&str>) -> Result<Target, Error> {
+        match name {
+            Some(name) => self
+                .targets
+                .iter()
+                .find(|t| t.name == name)
+                .cloned()
+                .ok_or_else(|| Error::Other(format!("No target named {name}"))),
+            None => Ok(self.targets.first().cloned().unwrap_or_else(|| Target {
+                name: "default".to_owned(),
+                executable: Some("a.out".to_owned()),
+                ..Default::default()
+            })),
+        }
+    }
+
     pub fn output_directory(&self) -> Result<PathBuf, Error> {
         let path = PathBuf::from(&self.project_directory).join("out");
         if !path.is_dir() {
@@ -170,3 +365,178 @@ impl Project {
         Ok(path)
     }
 }
+
+impl Target {
+    /// Target compiling the files of the project with `module` as top-level module
+    pub fn for_module(module: &str) -> Self {
+        Self {
+            name: module.to_owned(),
+            top: Some(module.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Lists the files to compile for this target
+    pub fn source_files(&self, project: &Project) -> Result<Vec<PathBuf>, Error> {
+        let mut project = project.clone();
+        if let Some(included_files) = &self.included_files {
+            project.included_files = included_files.clone();
+        }
+        project
+            .excluded_files
+            .extend(self.excluded_files.iter().cloned());
+        project.source_files()
+    }
+
+    /// Arguments of `iverilog` setting the parameters and macros of this target. The parameters
+    /// which are not prefixed by a module are the ones of the top-level module, which must then be
+    /// set since `iverilog` ignores unprefixed parameters.
+    pub fn compiler_args(&self) -> Result<Vec<String>, Error> {
+        let mut arguments = vec![];
+        for (name, value) in &self.parameters {
+            match &self.top {
+                _ if name.contains('.') => arguments.push(format!("-P{name}={value}")),
+                Some(top) => arguments.push(format!("-P{top}.{name}={value}")),
+                None => {
+                    return Err(Error::Other(format!(
+                        "Parameter {name} of target {} must be prefixed by its module, as the \
+                         top-level module is unknown",
+                        self.name
+                    )))
+                }
+            }
+        }
+        arguments.extend(self.defines.iter().map(|(name, value)| match value {
+            Some(value) => format!("-D{name}={value}"),
+            None => format!("-D{name}"),
+        }));
+        Ok(arguments)
+    }
+
+    /// Arguments of `vvp` to simulate `executable` with the runtime options and plusargs of this
+    /// target
+    pub fn simulator_args(&self, executable: &Path) -> Vec<String> {
+        let mut arguments = vec![];
+        if self.runtime.non_interactive {
+            arguments.push("-n".to_owned());
+        }
+        for path in &self.runtime.vpi_paths {
+            arguments.push(format!("-M{}", path.display()));
+        }
+        for module in &self.runtime.vpi_modules {
+            arguments.push(format!("-m{module}"));
+        }
+
+        arguments.push(executable.to_string_lossy().to_string());
+        // Extended arguments, which follow the executable
+        if let Some(format) = self.runtime.dump_format {
+            arguments.push(
+                match format {
+                    DumpFormat::Vcd => "-vcd",
+                    DumpFormat::Fst => "-fst",
+                    DumpFormat::Lxt2 => "-lxt2",
+                    DumpFormat::None => "-none",
+                }
+                .to_owned(),
+            );
+        }
+        arguments.extend(self.plusargs.iter().map(|(name, value)| match value {
+            Some(value) => format!("+{name}={value}"),
+            None => format!("+{name}"),
+        }));
+        arguments
+    }
+
+    /// Chooses the seed of a simulation of this target
+    pub fn choose_seed(&self) -> Option<u32> {
+        match self.seed? {
+            Seed::Random => Some(random_seed()),
+            Seed::Fixed(seed) => Some(seed),
+        }
+    }
+
+    /// Returns this target, passing `seed` to its simulations
+    pub fn with_seed(&self, seed: Option<u32>) -> Target {
+        let mut target = self.clone();
+        if let Some(seed) = seed {
+            target.seed = Some(Seed::Fixed(seed));
+            target.plusargs.insert(
+                self.seed_plusarg
+                    .clone()
+                    .unwrap_or_else(|| "seed".to_owned()),
+                Some(seed.to_string()),
+            );
+        }
+        target
+    }
+
+    /// Path of the executable produced by the compilation of this target
+    pub fn executable(&self, output_directory: &Path) -> PathBuf {
+        output_directory.join(
+            self.executable
+                .clone()
+                .unwrap_or_else(|| format!("{}.out", self.name)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn targets() {
+        let mut project: Project = serde_json::from_str("{}").unwrap();
+        let default = project.target(None).unwrap();
+        assert_eq!(default.executable(Path::new("out")), Path::new("out/a.out"));
+        assert!(project.target(Some("alu")).is_err());
+
+        project.targets = serde_json::from_str(
+            r#"[{"name": "alu", "top": "tb_alu", "includedFiles": ["alu*.v"]}, {"name": "fifo"}]"#,
+        )
+        .unwrap();
+        let alu = project.target(None).unwrap();
+        assert_eq!(alu.top.as_deref(), Some("tb_alu"));
+        assert_eq!(alu.executable(Path::new("out")), Path::new("out/alu.out"));
+        assert_eq!(project.target(Some("fifo")).unwrap().name, "fifo");
+    }
+
+    #[test]
+    fn simulator_args() {
+        let target: Target = serde_json::from_str(
+            r#"{
+                "name": "uart",
+                "plusargs": {"VERBOSE": null, "baud": "9600"},
+                "runtime": {"dumpFormat": "fst", "nonInteractive": true, "vpiModules": ["uart_model"]}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            target.simulator_args(Path::new("uart.out")),
+            vec![
+                "-n",
+                "-muart_model",
+                "uart.out",
+                "-fst",
+                "+VERBOSE",
+                "+baud=9600"
+            ]
+        );
+    }
+
+    #[test]
+    fn compiler_args() {
+        let mut target: Target = serde_json::from_str(
+            r#"{"name": "alu", "parameters": {"WIDTH": "8", "tb.dut.DEPTH": "4"}, "defines": {"FAST": null}}"#,
+        )
+        .unwrap();
+        assert!(target.compiler_args().is_err());
+
+        target.top = Some("tb".to_owned());
+        assert_eq!(
+            target.compiler_args().unwrap(),
+            vec!["-Ptb.WIDTH=8", "-Ptb.dut.DEPTH=4", "-DFAST"]
+        );
+    }
+}
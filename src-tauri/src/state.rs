@@ -1,16 +1,52 @@
-use std::sync::Mutex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
 
-use crate::project::Project;
+use crate::{
+    icarus::{
+        process::RunningProcess, transcript::TranscriptLine, vcd::VCDFile, vvp::SimulationRecord,
+    },
+    jobs::{Job, JobStatus},
+    project::Project,
+};
 
 pub type AppState<'r> = tauri::State<'r, Mutex<State>>;
 
+/// Number of runs whose transcript and options are kept, the oldest ones being dropped
+const KEPT_RUNS: usize = 200;
+/// Number of finished jobs kept, the oldest ones being dropped when a job starts
+const KEPT_JOBS: usize = 50;
+
 pub struct State {
     project: Option<Project>,
+    /// Dumps produced by the last simulation
+    waves: Vec<VCDFile>,
+    /// Output of the last runs, indexed by run id
+    transcripts: BTreeMap<u64, Vec<TranscriptLine>>,
+    /// Options of the last simulations, indexed by run id
+    records: BTreeMap<u64, SimulationRecord>,
+    next_run: u64,
+    /// External processes running, indexed by run id
+    processes: HashMap<u64, RunningProcess>,
+    /// Run which started each run started by another one, such as the tests of a test run
+    parents: HashMap<u64, u64>,
+    /// Background jobs, indexed by run id
+    jobs: BTreeMap<u64, Job>,
 }
 
 impl State {
     pub fn new(project: Option<Project>) -> Self {
-        Self { project }
+        Self {
+            project,
+            waves: vec![],
+            transcripts: BTreeMap::new(),
+            records: BTreeMap::new(),
+            next_run: 0,
+            processes: HashMap::new(),
+            parents: HashMap::new(),
+            jobs: BTreeMap::new(),
+        }
     }
 
     pub fn project(&self) -> Option<&Project> {
@@ -20,4 +56,100 @@ impl State {
     pub fn project_mut(&mut self) -> &mut Option<Project> {
         &mut self.project
     }
+
+    pub fn waves(&self) -> &[VCDFile] {
+        &self.waves
+    }
+
+    pub fn waves_mut(&mut self) -> &mut Vec<VCDFile> {
+        &mut self.waves
+    }
+
+    /// Allocates the id of a new simulation run
+    pub fn new_run(&mut self) -> u64 {
+        self.next_run += 1;
+        self.next_run
+    }
+
+    /// Allocates the id of a run started by the run `parent`
+    pub fn new_child_run(&mut self, parent: u64) -> u64 {
+        let run = self.new_run();
+        self.parents.insert(run, parent);
+        run
+    }
+
+    /// Forgets the runs started by `parent`, once it finished
+    pub fn remove_children(&mut self, parent: u64) {
+        self.parents.retain(|_, p| *p != parent);
+    }
+
+    pub fn transcript(&self, run: u64) -> Option<&[TranscriptLine]> {
+        self.transcripts.get(&run).map(Vec::as_slice)
+    }
+
+    pub fn set_transcript(&mut self, run: u64, transcript: Vec<TranscriptLine>) {
+        self.transcripts.insert(run, transcript);
+        truncate(&mut self.transcripts, KEPT_RUNS, run);
+    }
+
+    pub fn record(&self, run: u64) -> Option<&SimulationRecord> {
+        self.records.get(&run)
+    }
+
+    pub fn set_record(&mut self, run: u64, record: SimulationRecord) {
+        self.records.insert(run, record);
+        truncate(&mut self.records, KEPT_RUNS, run);
+    }
+
+    /// Lists the processes of `run` and of the runs it started
+    pub fn processes_of(&self, run: u64) -> impl Iterator<Item = &RunningProcess> {
+        self.processes
+            .iter()
+            .filter(move |(id, _)| **id == run || self.parents.get(id) == Some(&run))
+            .map(|(_, process)| process)
+    }
+
+    pub fn processes(&self) -> impl Iterator<Item = &RunningProcess> {
+        self.processes.values()
+    }
+
+    pub fn add_process(&mut self, run: u64, process: RunningProcess) {
+        self.processes.insert(run, process);
+    }
+
+    pub fn remove_process(&mut self, run: u64) {
+        self.processes.remove(&run);
+    }
+
+    pub fn jobs(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.values()
+    }
+
+    pub fn job_mut(&mut self, id: u64) -> Option<&mut Job> {
+        self.jobs.get_mut(&id)
+    }
+
+    pub fn add_job(&mut self, job: Job) {
+        self.jobs.insert(job.id, job);
+
+        let finished = self
+            .jobs
+            .values()
+            .filter(|job| job.status != JobStatus::Running)
+            .map(|job| job.id)
+            .collect::<Vec<_>>();
+        for id in &finished[..finished.len().saturating_sub(KEPT_JOBS)] {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Removes the entries of the oldest runs of `runs` but `current`, keeping `kept` of them
+fn truncate<T>(runs: &mut BTreeMap<u64, T>, kept: usize, current: u64) {
+    while runs.len() > kept {
+        let Some(oldest) = runs.keys().find(|run| **run != current).copied() else {
+            break;
+        };
+        runs.remove(&oldest);
+    }
 }
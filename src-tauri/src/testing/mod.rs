@@ -0,0 +1,724 @@
+//! Test runner, compiling and simulating each testbench of the project in isolation and deciding
+//! whether it passed from its exit code, its messages and the markers it printed.
+
+pub mod report;
+pub mod sweep;
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    error::Error,
+    icarus::{
+        iverilog::{compile_project, CompilationOutcome, TIME_LIMIT_MESSAGE},
+        messages::{parse_messages, Severity, SimulationMessage},
+        top::root_modules,
+        transcript::{Stop, Stream, TranscriptLine},
+        vvp::{dump_paths, run_simulation},
+    },
+    jobs::{self, JobKind},
+    project::{random_seed, Project, Seed, Target},
+    state::{AppState, State},
+    util::Digest,
+};
+
+/// Names of the root modules considered as testbenches, if the project does not specify any
+pub const DEFAULT_PATTERN: &str = "^(tb|test)_|_(tb|test)$";
+/// Maximum number of seeds of [`run_seeds`]
+const MAX_SEEDS: usize = 10_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestConfig {
+    /// Regular expression matching the names of the root modules which are testbenches
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Targets or modules run as tests in addition to the discovered ones
+    #[serde(default)]
+    pub testbenches: Vec<String>,
+    /// Text that the passing testbenches must print
+    #[serde(default)]
+    pub pass_marker: Option<String>,
+    /// Text printed by the failing testbenches
+    #[serde(default)]
+    pub fail_marker: Option<String>,
+    /// Number of tests run at the same time, the number of CPUs by default
+    #[serde(default)]
+    pub jobs: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum TestStatus {
+    Passed,
+    Failed {
+        reason: String,
+        /// Error message which made the test fail, if any
+        message: Option<SimulationMessage>,
+    },
+    /// The test could not be run, e.g. because it does not compile
+    Error {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestResult {
+    pub name: String,
+    /// Run of the test, to get its transcript and messages
+    pub run: u64,
+    #[serde(flatten)]
+    pub status: TestStatus,
+    /// Seed passed to the simulation
+    pub seed: Option<u32>,
+    /// Duration of the compilation and simulation, in milliseconds
+    pub duration: u64,
+    pub messages: Vec<SimulationMessage>,
+    pub transcript: Vec<TranscriptLine>,
+    /// Dumps written by the simulation
+    pub dumps: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    /// Duration of the whole run, in milliseconds
+    pub duration: u64,
+    pub tests: Vec<TestResult>,
+}
+
+impl TestSummary {
+    pub fn new(tests: Vec<TestResult>, duration: Duration) -> Self {
+        let count = |f: fn(&TestStatus) -> bool| tests.iter().filter(|t| f(&t.status)).count();
+        Self {
+            passed: count(|s| matches!(s, TestStatus::Passed)),
+            failed: count(|s| matches!(s, TestStatus::Failed { .. })),
+            errors: count(|s| matches!(s, TestStatus::Error { .. })),
+            duration: duration.as_millis() as u64,
+            tests,
+        }
+    }
+}
+
+/// Payload of the `test-progress` events, sent when a test of a run starts or finishes
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+    job: u64,
+    total: usize,
+    finished: usize,
+    passed: usize,
+    failed: usize,
+    errors: usize,
+    /// Names of the tests running
+    running: Vec<String>,
+}
+
+impl Progress {
+    fn report(&self, state: &Mutex<State>, app: &AppHandle) {
+        jobs::progress(
+            self.job,
+            &format!("{}/{} tests finished", self.finished, self.total),
+            state,
+            app,
+        );
+        if let Err(e) = app.emit_all("test-progress", self.clone()) {
+            tracing::warn!("Could not send test progress: {e:?}");
+        }
+    }
+}
+
+/// Payload of the `test-result` events, sent after each test of a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultEvent<'a> {
+    job: u64,
+    result: &'a TestResult,
+}
+
+/// Lists the testbenches of the project: the ones of its configuration, then the root modules
+/// whose names match the pattern of the configuration
+pub fn discover(project: &Project) -> Result<Vec<Target>, Error> {
+    let config = &project.tests;
+    let pattern = Regex::new(config.pattern.as_deref().unwrap_or(DEFAULT_PATTERN))
+        .map_err(|e| Error::Other(format!("Invalid testbench pattern: {e}")))?;
+
+    let mut tests = config
+        .testbenches
+        .iter()
+        .map(|name| {
+            project
+                .target(Some(name))
+                .unwrap_or_else(|_| Target::for_module(name))
+        })
+        .collect::<Vec<_>>();
+    for root in root_modules(&project.source_files()?)? {
+        let known = tests
+            .iter()
+            .any(|t| t.name == root.name || t.top.as_ref() == Some(&root.name));
+        if !known && pattern.is_match(&root.name) {
+            tests.push(Target::for_module(&root.name));
+        }
+    }
+    Ok(tests)
+}
+
+/// Decides whether a simulation which ran to its end passed
+pub fn verdict(
+    exit_code: Option<i32>,
+    transcript: &[TranscriptLine],
+    messages: &[SimulationMessage],
+    config: &TestConfig,
+) -> TestStatus {
+    let printed = |marker: &str| {
+        transcript
+            .iter()
+            .any(|l| l.stream == Stream::Stdout && l.line.contains(marker))
+    };
+    let failed = |reason: String| TestStatus::Failed {
+        reason,
+        message: None,
+    };
+
+    if let Some(marker) = config.fail_marker.as_deref().filter(|m| printed(m)) {
+        failed(format!("Printed {marker}"))
+    } else if let Some(message) = messages.iter().find(|m| m.severity >= Severity::Error) {
+        TestStatus::Failed {
+            reason: match message.severity {
+                Severity::Fatal => "Fatal error".to_owned(),
+                _ => "Error".to_owned(),
+            },
+            message: Some(message.clone()),
+        }
+    } else if let Some(code) = exit_code.filter(|c| *c != 0) {
+        failed(format!("vvp exited with code {code}"))
+    } else if let Some(marker) = config.pass_marker.as_deref().filter(|m| !printed(m)) {
+        failed(format!("Did not print {marker}"))
+    } else {
+        TestStatus::Passed
+    }
+}
+
+/// Describes the first error of a failed compilation
+fn compilation_error(outcome: &CompilationOutcome) -> String {
+    let CompilationOutcome::Failure { errors } = outcome else {
+        return "Compilation failed".to_owned();
+    };
+    let mut files = errors.iter().collect::<Vec<_>>();
+    files.sort_by_key(|(file, _)| *file);
+
+    files
+        .into_iter()
+        .find_map(|(file, errors)| {
+            let mut lines = errors.lines.iter().collect::<Vec<_>>();
+            lines.sort_by_key(|(line, _)| **line);
+            lines
+                .first()
+                .and_then(|(line, messages)| Some(format!("{file}:{line}: {}", messages.first()?)))
+                .or_else(|| Some(format!("{file}: {}", errors.global.first()?)))
+        })
+        .map_or("Compilation failed".to_owned(), |error| {
+            format!("Compilation failed: {error}")
+        })
+}
+
+/// Name of the directory of a test, with the characters which could escape the tests directory
+/// replaced, so that a target named `../x` or `a/b` stays inside it. A digest of the name is
+/// appended when it was changed, so that the tests of a run keep distinct directories.
+fn directory_name(name: &str) -> String {
+    let sanitized = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if !sanitized.is_empty() && sanitized == name {
+        sanitized
+    } else {
+        format!(
+            "{sanitized}-{}",
+            &Digest::default().update(name).finish()[..8]
+        )
+    }
+}
+
+/// Result of a test which did not run yet
+fn pending_result(target: &Target, run: u64) -> TestResult {
+    TestResult {
+        name: target.name.clone(),
+        run,
+        status: TestStatus::Passed,
+        seed: target.choose_seed(),
+        duration: 0,
+        messages: vec![],
+        transcript: vec![],
+        dumps: vec![],
+    }
+}
+
+/// Result of a test which could not be run because of `error`. Only the cancellations are
+/// returned as errors, so that the other tests of the run go on.
+fn error_result(
+    target: &Target,
+    run: u64,
+    start: Instant,
+    error: Error,
+) -> Result<TestResult, Error> {
+    let reason = match error {
+        Error::Cancelled => return Err(error),
+        Error::Other(message) | Error::TimeLimit(message) => message,
+        Error::IO(e) => e.to_string(),
+        e => format!("{e:?}"),
+    };
+    tracing::warn!("Could not run test {}: {reason}", target.name);
+
+    let mut result = pending_result(target, run);
+    result.status = TestStatus::Error { reason };
+    result.duration = start.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+/// Result of a test whose compilation failed, with the output of the compiler
+fn compilation_result(
+    target: &Target,
+    run: u64,
+    start: Instant,
+    outcome: &CompilationOutcome,
+    state: &Mutex<State>,
+) -> TestResult {
+    let mut result = pending_result(target, run);
+    result.status = TestStatus::Error {
+        reason: compilation_error(outcome),
+    };
+    result.transcript = state
+        .lock()
+        .unwrap()
+        .transcript(run)
+        .map(<[_]>::to_vec)
+        .unwrap_or_default();
+    result.duration = start.elapsed().as_millis() as u64;
+    result
+}
+
+/// Compiles and simulates `target` as a run started by `parent`. Each test has its own directory
+/// in the output directory, so that the files written by the simulations do not collide.
+pub fn run_test(
+    project: &Project,
+    target: &Target,
+    parent: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<TestResult, Error> {
+    let run = state.lock().unwrap().new_child_run(parent);
+    let start = Instant::now();
+    let test = || {
+        let directory = project
+            .output_directory()?
+            .join("tests")
+            .join(directory_name(&target.name));
+        fs::create_dir_all(&directory)?;
+
+        let outcome = compile_project(project, target, &directory, run, state, app)?;
+        if let CompilationOutcome::Failure { .. } = outcome {
+            return Ok(compilation_result(target, run, start, &outcome, state));
+        }
+        let result = pending_result(target, run);
+        simulate_test(project, target, &directory, result, start, state, app)
+    };
+    test().or_else(|e| error_result(target, run, start, e))
+}
+
+/// Simulates the executable of `target` in `directory` as the run of `result`, and decides whether
+/// the test passed
+fn simulate_test(
+    project: &Project,
+    target: &Target,
+    directory: &Path,
+    mut result: TestResult,
+    start: Instant,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<TestResult, Error> {
+    let run = result.run;
+    let output = run_simulation(
+        target,
+        result.seed,
+        directory,
+        run,
+        project.limits.wall_clock.map(Duration::from_secs),
+        state,
+        app,
+    )?;
+    state
+        .lock()
+        .unwrap()
+        .set_transcript(run, output.transcript.clone());
+
+    result.messages = parse_messages(&output.transcript);
+    result.status = match output.stop {
+        Some(Stop::Cancelled) => return Err(Error::Cancelled),
+        Some(Stop::TimeLimit) => TestStatus::Failed {
+            reason: format!(
+                "Stopped after {} s",
+                project.limits.wall_clock.unwrap_or_default()
+            ),
+            message: None,
+        },
+        None if output
+            .transcript
+            .iter()
+            .any(|l| l.line == TIME_LIMIT_MESSAGE) =>
+        {
+            TestStatus::Failed {
+                reason: "Simulated time limit reached".to_owned(),
+                message: None,
+            }
+        }
+        None => verdict(
+            output.status.code(),
+            &output.transcript,
+            &result.messages,
+            &project.tests,
+        ),
+    };
+    result.dumps = dump_paths(&output.transcript, directory);
+    result.transcript = output.transcript;
+    result.duration = start.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+/// Runs `tests` as the job `job`, running up to the number of jobs of the configuration at the
+/// same time. The results are in the order of `tests`.
+pub fn run_all(
+    project: &Project,
+    tests: &[Target],
+    job: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+) -> Result<TestSummary, Error> {
+    run_pool(project, tests, job, state, app, |test| {
+        run_test(project, test, job, state, app)
+    })
+}
+
+/// Runs `tests` with `run_one` as the job `job`, in a pool of workers
+fn run_pool<F>(
+    project: &Project,
+    tests: &[Target],
+    job: u64,
+    state: &Mutex<State>,
+    app: &AppHandle,
+    run_one: F,
+) -> Result<TestSummary, Error>
+where
+    F: Fn(&Target) -> Result<TestResult, Error> + Sync,
+{
+    let start = Instant::now();
+    let workers = project
+        .tests
+        .jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .clamp(1, tests.len().max(1));
+    tracing::info!("Running {} tests with {workers} workers", tests.len());
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; tests.len()]);
+    let error = Mutex::new(None);
+    let progress = Mutex::new(Progress {
+        job,
+        total: tests.len(),
+        ..Default::default()
+    });
+    progress.lock().unwrap().report(state, app);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                // Stops taking tests after the first error, such as a cancellation
+                while error.lock().unwrap().is_none() {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(test) = tests.get(i) else {
+                        break;
+                    };
+                    {
+                        let mut progress = progress.lock().unwrap();
+                        progress.running.push(test.name.clone());
+                        progress.report(state, app);
+                    }
+
+                    let result = run_one(test);
+                    let mut progress = progress.lock().unwrap();
+                    progress.running.retain(|name| name != &test.name);
+                    progress.finished += 1;
+                    match result {
+                        Ok(result) => {
+                            match result.status {
+                                TestStatus::Passed => progress.passed += 1,
+                                TestStatus::Failed { .. } => progress.failed += 1,
+                                TestStatus::Error { .. } => progress.errors += 1,
+                            }
+                            progress.report(state, app);
+                            if let Err(e) = app.emit_all(
+                                "test-result",
+                                ResultEvent {
+                                    job,
+                                    result: &result,
+                                },
+                            ) {
+                                tracing::warn!("Could not send test result: {e:?}");
+                            }
+                            results.lock().unwrap()[i] = Some(result);
+                        }
+                        Err(e) => {
+                            progress.report(state, app);
+                            error.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e);
+    }
+    let results = results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect();
+    Ok(TestSummary::new(results, start.elapsed()))
+}
+
+/// Lists the testbenches of the project
+#[tauri::command]
+pub fn discover_tests(state: AppState<'_>) -> Result<Vec<Target>, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    discover(&project)
+}
+
+/// Starts running the testbenches called `names`, or all of them, in the background, returning
+/// the id of the job
+#[tauri::command]
+pub fn run_tests(
+    names: Option<Vec<String>>,
+    state: AppState<'_>,
+    app: AppHandle,
+) -> Result<u64, Error> {
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    Ok(jobs::spawn(
+        JobKind::Tests,
+        &mut state,
+        &app,
+        move |job, state, app| {
+            jobs::progress(job, "Discovering tests", state, app);
+            let mut tests = discover(&project)?;
+            if let Some(names) = names {
+                if let Some(name) = names.iter().find(|n| !tests.iter().any(|t| &&t.name == n)) {
+                    return Err(Error::Other(format!("No testbench named {name}")));
+                }
+                tests.retain(|t| names.contains(&t.name));
+            }
+            run_all(&project, &tests, job, state, app)
+        },
+    ))
+}
+
+/// Starts running the testbench `testbench` `count` times with different random seeds in the
+/// background, to find the seeds making it fail, returning the id of the job. The testbench is
+/// compiled once, and only the dumps of the failing seeds are kept.
+#[tauri::command]
+pub fn run_seeds(
+    testbench: String,
+    count: usize,
+    state: AppState<'_>,
+    app: AppHandle,
+) -> Result<u64, Error> {
+    if count == 0 || count > MAX_SEEDS {
+        return Err(Error::Other(format!(
+            "The number of seeds must be between 1 and {MAX_SEEDS}"
+        )));
+    }
+    let mut seeds = HashSet::new();
+    while seeds.len() < count {
+        seeds.insert(random_seed());
+    }
+
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    let base = project
+        .target(Some(&testbench))
+        .unwrap_or_else(|_| Target::for_module(&testbench));
+
+    Ok(jobs::spawn(
+        JobKind::Tests,
+        &mut state,
+        &app,
+        move |job, state, app| {
+            let directory = project
+                .output_directory()?
+                .join("tests")
+                .join(directory_name(&base.name));
+            let seeds_directory = directory.join("seeds");
+            if seeds_directory.exists() {
+                fs::remove_dir_all(&seeds_directory)?;
+            }
+            fs::create_dir_all(&directory)?;
+
+            let start = Instant::now();
+            jobs::progress(job, "Compiling", state, app);
+            let run = state.lock().unwrap().new_child_run(job);
+            let outcome = compile_project(&project, &base, &directory, run, state, app)?;
+            if let CompilationOutcome::Failure { .. } = outcome {
+                let result = compilation_result(&base, run, start, &outcome, state);
+                return Ok(TestSummary::new(vec![result], start.elapsed()));
+            }
+
+            let executable = base.executable(&directory).to_string_lossy().into_owned();
+            let tests = seeds
+                .into_iter()
+                .map(|seed| {
+                    let mut target = base.clone();
+                    target.name = format!("{}-seed-{seed}", base.name);
+                    target.executable = Some(executable.clone());
+                    target.seed = Some(Seed::Fixed(seed));
+                    target
+                })
+                .collect::<Vec<_>>();
+            run_pool(&project, &tests, job, state, app, |test| {
+                let run = state.lock().unwrap().new_child_run(job);
+                let start = Instant::now();
+                let seed = || {
+                    let directory = seeds_directory.join(directory_name(&test.name));
+                    fs::create_dir_all(&directory)?;
+                    let result = pending_result(test, run);
+                    let mut result =
+                        simulate_test(&project, test, &directory, result, start, state, app)?;
+                    if result.status == TestStatus::Passed {
+                        fs::remove_dir_all(&directory)?;
+                        result.dumps.clear();
+                    }
+                    Ok(result)
+                };
+                seed().or_else(|e| error_result(test, run, start, e))
+            })
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transcript(lines: &[&str]) -> Vec<TranscriptLine> {
+        lines
+            .iter()
+            .map(|line| TranscriptLine {
+                stream: Stream::Stdout,
+                line: line.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verdicts() {
+        let config = TestConfig {
+            pass_marker: Some("PASS".to_owned()),
+            fail_marker: Some("FAIL".to_owned()),
+            ..Default::default()
+        };
+        let check = |code, lines: &[&str]| {
+            let transcript = transcript(lines);
+            verdict(code, &transcript, &parse_messages(&transcript), &config)
+        };
+
+        assert_eq!(check(Some(0), &["3 vectors", "PASS"]), TestStatus::Passed);
+        assert_eq!(
+            check(Some(0), &["3 vectors"]),
+            TestStatus::Failed {
+                reason: "Did not print PASS".to_owned(),
+                message: None
+            }
+        );
+        assert_eq!(
+            check(Some(0), &["FAIL at vector 2", "PASS"]),
+            TestStatus::Failed {
+                reason: "Printed FAIL".to_owned(),
+                message: None
+            }
+        );
+        assert_eq!(
+            check(Some(1), &["PASS"]),
+            TestStatus::Failed {
+                reason: "vvp exited with code 1".to_owned(),
+                message: None
+            }
+        );
+
+        let status = check(
+            Some(1),
+            &[
+                "WARNING: tb.v:3: slow",
+                "       Time: 5 Scope: tb",
+                "FATAL: tb.v:9: overflow",
+                "PASS",
+            ],
+        );
+        let TestStatus::Failed {
+            reason,
+            message: Some(message),
+        } = status
+        else {
+            panic!("Unexpected status {status:?}");
+        };
+        assert_eq!(reason, "Fatal error");
+        assert_eq!(message.line, Some(9));
+    }
+
+    #[test]
+    fn directory_names() {
+        assert_eq!(directory_name("tb_uart-seed-3"), "tb_uart-seed-3");
+        assert!(directory_name("../../etc").starts_with("______etc-"));
+        assert!(directory_name("").starts_with("-"));
+
+        // Names only differing by the replaced characters keep distinct directories
+        let names = ["a.b", "a_b", "a/b", "sweep/x=1", "sweep_x_1", "sweep_x=1"];
+        let directories = names
+            .iter()
+            .map(|n| directory_name(n))
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(directories.len(), names.len());
+        assert!(directories.iter().all(|d| d
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')));
+    }
+}
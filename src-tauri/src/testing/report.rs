@@ -0,0 +1,368 @@
+//! Reports of the test runs, as JUnit XML for CI pipelines or as JSON.
+//!
+//! The JSON report has its own schema, versioned independently of the structures returned to the
+//! frontend, so that external tools can rely on it.
+
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{TestResult, TestStatus, TestSummary};
+use crate::{
+    error::Error,
+    icarus::transcript::{Stream, TranscriptLine},
+    state::AppState,
+};
+
+/// Version of the schema of the JSON reports, to increment on incompatible changes
+const JSON_REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportFormat {
+    Junit,
+    Json,
+}
+
+impl ReportFormat {
+    fn default_file(&self) -> &'static str {
+        match self {
+            ReportFormat::Junit => "junit.xml",
+            ReportFormat::Json => "test-report.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonReport {
+    pub version: u32,
+    pub project: String,
+    /// Generation time, in milliseconds since the UNIX epoch
+    pub timestamp: u64,
+    pub tests: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    /// Duration of the run, in seconds
+    pub duration: f64,
+    pub testbenches: Vec<JsonTest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTest {
+    pub name: String,
+    /// `passed`, `failed` or `error`
+    pub status: &'static str,
+    /// Duration, in seconds
+    pub duration: f64,
+    /// Seed passed to the simulation
+    pub seed: Option<u32>,
+    pub failure: Option<JsonFailure>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonFailure {
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Simulation time of the message which made the test fail, with its unit
+    pub time: Option<String>,
+}
+
+fn seconds(milliseconds: u64) -> f64 {
+    milliseconds as f64 / 1000.
+}
+
+fn output(transcript: &[TranscriptLine], stream: Stream) -> String {
+    transcript
+        .iter()
+        .filter(|l| l.stream == stream)
+        .map(|l| format!("{}\n", l.line))
+        .collect()
+}
+
+/// Failure of a test, `None` if it passed
+fn failure(test: &TestResult) -> Option<JsonFailure> {
+    match &test.status {
+        TestStatus::Passed => None,
+        TestStatus::Failed { reason, message } => Some(JsonFailure {
+            message: match message {
+                Some(m) if !m.message.is_empty() => format!("{reason}: {}", m.message),
+                _ => reason.clone(),
+            },
+            file: message.as_ref().and_then(|m| m.file.clone()),
+            line: message.as_ref().and_then(|m| m.line),
+            time: message
+                .as_ref()
+                .and_then(|m| Some(format!("{} {}", m.time?, m.unit.as_deref().unwrap_or(""))))
+                .map(|t| t.trim_end().to_owned()),
+        }),
+        TestStatus::Error { reason } => Some(JsonFailure {
+            message: reason.clone(),
+            file: None,
+            line: None,
+            time: None,
+        }),
+    }
+}
+
+/// Builds the JSON report of a test run of `project`
+pub fn json_report(summary: &TestSummary, project: &str) -> JsonReport {
+    JsonReport {
+        version: JSON_REPORT_VERSION,
+        project: project.to_owned(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        tests: summary.tests.len(),
+        passed: summary.passed,
+        failed: summary.failed,
+        errors: summary.errors,
+        duration: seconds(summary.duration),
+        testbenches: summary
+            .tests
+            .iter()
+            .map(|test| JsonTest {
+                name: test.name.clone(),
+                status: match test.status {
+                    TestStatus::Passed => "passed",
+                    TestStatus::Failed { .. } => "failed",
+                    TestStatus::Error { .. } => "error",
+                },
+                duration: seconds(test.duration),
+                seed: test.seed,
+                failure: failure(test),
+                stdout: output(&test.transcript, Stream::Stdout),
+                stderr: output(&test.transcript, Stream::Stderr),
+            })
+            .collect(),
+    }
+}
+
+/// Escapes a string to use it in XML text or attributes, removing the characters XML forbids
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes the JUnit XML report of a test run of `project`
+pub fn junit_report(summary: &TestSummary, project: &str) -> String {
+    let report = json_report(summary, project);
+    let project = escape(project);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    // Writing to a String cannot fail
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="{project}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+        report.tests, report.failed, report.errors, report.duration
+    );
+    let _ = writeln!(
+        xml,
+        r#"  <testsuite name="{project}" tests="{}" failures="{}" errors="{}" time="{:.3}">"#,
+        report.tests, report.failed, report.errors, report.duration
+    );
+    for test in &report.testbenches {
+        let location = test
+            .failure
+            .as_ref()
+            .and_then(|f| {
+                Some(format!(
+                    r#" file="{}" line="{}""#,
+                    escape(f.file.as_ref()?),
+                    f.line?
+                ))
+            })
+            .unwrap_or_default();
+        let _ = writeln!(
+            xml,
+            r#"    <testcase name="{}" classname="{project}" time="{:.3}"{location}>"#,
+            escape(&test.name),
+            test.duration
+        );
+
+        if let Some(failure) = &test.failure {
+            let element = if test.status == "error" {
+                "error"
+            } else {
+                "failure"
+            };
+            let mut details = match (&failure.file, failure.line) {
+                (Some(file), Some(line)) => format!("{file}:{line}: {}", failure.message),
+                _ => failure.message.clone(),
+            };
+            if let Some(time) = &failure.time {
+                details.push_str(&format!(" (at {time})"));
+            }
+            let _ = writeln!(
+                xml,
+                r#"      <{element} message="{}">{}</{element}>"#,
+                escape(&failure.message),
+                escape(&details)
+            );
+        }
+        if !test.stdout.is_empty() {
+            let _ = writeln!(
+                xml,
+                "      <system-out>{}</system-out>",
+                escape(&test.stdout)
+            );
+        }
+        if !test.stderr.is_empty() {
+            let _ = writeln!(
+                xml,
+                "      <system-err>{}</system-err>",
+                escape(&test.stderr)
+            );
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Writes the report of a test run to `path`, or to the output directory of the project, returning
+/// the path of the report
+#[tauri::command]
+pub fn write_test_report(
+    summary: TestSummary,
+    format: ReportFormat,
+    path: Option<PathBuf>,
+    state: AppState<'_>,
+) -> Result<PathBuf, Error> {
+    let project = state
+        .lock()
+        .unwrap()
+        .project()
+        .ok_or(Error::NoProject)?
+        .clone();
+    let path = match path {
+        Some(path) => path,
+        None => project.output_directory()?.join(format.default_file()),
+    };
+
+    let content = match format {
+        ReportFormat::Junit => junit_report(&summary, &project.name),
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(&json_report(&summary, &project.name))
+                .map_err(|e| Error::Other(format!("Could not serialize the report: {e}")))?
+        }
+    };
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::messages::{Severity, SimulationMessage};
+
+    fn summary() -> TestSummary {
+        let line = |stream, line: &str| TranscriptLine {
+            stream,
+            line: line.to_owned(),
+        };
+        TestSummary {
+            passed: 1,
+            failed: 1,
+            errors: 0,
+            duration: 1500,
+            tests: vec![
+                TestResult {
+                    name: "tb_adder".to_owned(),
+                    run: 2,
+                    status: TestStatus::Passed,
+                    seed: None,
+                    duration: 500,
+                    messages: vec![],
+                    transcript: vec![line(Stream::Stdout, "PASS")],
+                    dumps: vec![],
+                },
+                TestResult {
+                    name: "tb_fifo".to_owned(),
+                    run: 3,
+                    status: TestStatus::Failed {
+                        reason: "Error".to_owned(),
+                        message: Some(SimulationMessage {
+                            severity: Severity::Error,
+                            message: "count < 0".to_owned(),
+                            time: Some(40),
+                            unit: Some("1ns".to_owned()),
+                            file: Some("tb_fifo.v".to_owned()),
+                            line: Some(12),
+                            scope: Some("tb_fifo".to_owned()),
+                        }),
+                    },
+                    seed: Some(42),
+                    duration: 1000,
+                    messages: vec![],
+                    transcript: vec![
+                        line(Stream::Stdout, "ERROR: tb_fifo.v:12: count < 0"),
+                        line(Stream::Stderr, "\u{1b}warning"),
+                    ],
+                    dumps: vec![],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn junit() {
+        let xml = junit_report(&summary(), "fifo & adder");
+        assert!(xml.contains(
+            r#"<testsuites name="fifo &amp; adder" tests="2" failures="1" errors="0" time="1.500">"#
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"tb_adder\" classname=\"fifo &amp; adder\" time=\"0.500\">\n      \
+             <system-out>PASS\n</system-out>\n    </testcase>"
+        ));
+        assert!(xml.contains(
+            r#"<testcase name="tb_fifo" classname="fifo &amp; adder" time="1.000" file="tb_fifo.v" line="12">"#
+        ));
+        assert!(xml.contains(
+            r#"<failure message="Error: count &lt; 0">tb_fifo.v:12: Error: count &lt; 0 (at 40 1ns)</failure>"#
+        ));
+        assert!(xml.contains("<system-err>warning\n</system-err>"));
+    }
+
+    #[test]
+    fn json() {
+        let report = json_report(&summary(), "fifo");
+        assert_eq!(report.version, JSON_REPORT_VERSION);
+        assert_eq!(report.tests, 2);
+        assert_eq!(report.testbenches[0].status, "passed");
+        assert_eq!(report.testbenches[1].seed, Some(42));
+        assert_eq!(
+            report.testbenches[1].failure,
+            Some(JsonFailure {
+                message: "Error: count < 0".to_owned(),
+                file: Some("tb_fifo.v".to_owned()),
+                line: Some(12),
+                time: Some("40 1ns".to_owned()),
+            })
+        );
+    }
+}
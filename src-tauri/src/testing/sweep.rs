@@ -0,0 +1,241 @@
+//! Sweeps, running a testbench for every combination of values of some of its parameters,
+//! macros and plusargs.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use super::{run_all, TestResult};
+use crate::{
+    error::Error,
+    jobs::{self, JobKind},
+    project::{Project, Target},
+    state::AppState,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sweep {
+    pub name: String,
+    /// Target or module swept
+    pub testbench: String,
+    /// Values of the parameters, see [`Target::parameters`]
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Vec<String>>,
+    /// Values of the macros, see [`Switch`]
+    #[serde(default)]
+    pub defines: BTreeMap<String, Vec<Switch>>,
+    /// Values of the plusargs, see [`Switch`]
+    #[serde(default)]
+    pub plusargs: BTreeMap<String, Vec<Switch>>,
+}
+
+/// Value of a swept macro or plusarg. As in [`Target::defines`] and [`Target::plusargs`], `null`
+/// sets it without a value (`-DFAST`, `+VERBOSE`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Switch {
+    /// `false` leaves the macro or plusarg unset, `true` sets it without a value
+    Flag(bool),
+    Value(Option<String>),
+}
+
+impl Switch {
+    /// Value of the macro or plusarg, `None` if it is unset
+    fn value(&self) -> Option<Option<String>> {
+        match self {
+            Switch::Flag(false) => None,
+            Switch::Flag(true) => Some(None),
+            Switch::Value(value) => Some(value.clone()),
+        }
+    }
+}
+
+/// Combination of values of a sweep. The parameters, macros and plusargs which are not swept keep
+/// the values of the testbench.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepPoint {
+    pub parameters: BTreeMap<String, String>,
+    pub defines: BTreeMap<String, Switch>,
+    pub plusargs: BTreeMap<String, Switch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepResult {
+    #[serde(flatten)]
+    pub point: SweepPoint,
+    #[serde(flatten)]
+    pub result: TestResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepReport {
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    /// Duration of the sweep, in milliseconds
+    pub duration: u64,
+    /// Results of the combinations, the values of the last swept variables changing first
+    pub results: Vec<SweepResult>,
+}
+
+/// Value of a swept variable
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Parameter(&'a str, &'a str),
+    Define(&'a str, &'a Switch),
+    Plusarg(&'a str, &'a Switch),
+}
+
+impl Sweep {
+    /// Lists the combinations of values of the sweep
+    pub fn points(&self) -> Vec<SweepPoint> {
+        let mut dimensions: Vec<Vec<Value>> = vec![];
+        for (name, values) in &self.parameters {
+            dimensions.push(values.iter().map(|v| Value::Parameter(name, v)).collect());
+        }
+        for (name, values) in &self.defines {
+            dimensions.push(values.iter().map(|v| Value::Define(name, v)).collect());
+        }
+        for (name, values) in &self.plusargs {
+            dimensions.push(values.iter().map(|v| Value::Plusarg(name, v)).collect());
+        }
+
+        let mut points = vec![SweepPoint::default()];
+        for dimension in dimensions {
+            points = points
+                .into_iter()
+                .flat_map(|point| {
+                    dimension.iter().map(move |value| {
+                        let mut point = point.clone();
+                        match *value {
+                            Value::Parameter(name, v) => {
+                                point.parameters.insert(name.to_owned(), v.to_owned());
+                            }
+                            Value::Define(name, v) => {
+                                point.defines.insert(name.to_owned(), v.clone());
+                            }
+                            Value::Plusarg(name, v) => {
+                                point.plusargs.insert(name.to_owned(), v.clone());
+                            }
+                        }
+                        point
+                    })
+                })
+                .collect();
+        }
+        points
+    }
+
+    /// Lists the combinations of values of the sweep, with the targets running them
+    pub fn expand(&self, project: &Project) -> Vec<(SweepPoint, Target)> {
+        let base = project
+            .target(Some(&self.testbench))
+            .unwrap_or_else(|_| Target::for_module(&self.testbench));
+
+        self.points()
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let mut target = base.clone();
+                target.name = format!("{}-{i}", self.name);
+                target.executable = None;
+                target.parameters.extend(point.parameters.clone());
+                for (name, value) in &point.defines {
+                    match value.value() {
+                        Some(value) => target.defines.insert(name.clone(), value),
+                        None => target.defines.remove(name),
+                    };
+                }
+                for (name, value) in &point.plusargs {
+                    match value.value() {
+                        Some(value) => target.plusargs.insert(name.clone(), value),
+                        None => target.plusargs.remove(name),
+                    };
+                }
+                (point, target)
+            })
+            .collect()
+    }
+}
+
+/// Starts running the sweep called `name` in the background, returning the id of the job
+#[tauri::command]
+pub fn run_sweep(name: String, state: AppState<'_>, app: AppHandle) -> Result<u64, Error> {
+    let mut state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?.clone();
+    let sweep = project
+        .sweeps
+        .iter()
+        .find(|s| s.name == name)
+        .cloned()
+        .ok_or_else(|| Error::Other(format!("No sweep named {name}")))?;
+
+    Ok(jobs::spawn(
+        JobKind::Sweep,
+        &mut state,
+        &app,
+        move |job, state, app| {
+            let (points, targets): (Vec<_>, Vec<_>) = sweep.expand(&project).into_iter().unzip();
+            let summary = run_all(&project, &targets, job, state, app)?;
+            Ok(SweepReport {
+                name: sweep.name,
+                passed: summary.passed,
+                failed: summary.failed,
+                errors: summary.errors,
+                duration: summary.duration,
+                results: points
+                    .into_iter()
+                    .zip(summary.tests)
+                    .map(|(point, result)| SweepResult { point, result })
+                    .collect(),
+            })
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expansion() {
+        let project: Project = serde_json::from_str(
+            r#"{"targets": [{"name": "alu", "top": "tb_alu", "defines": {"FAST": null}, "plusargs": {"VERBOSE": null}}]}"#,
+        )
+        .unwrap();
+        let sweep: Sweep = serde_json::from_str(
+            r#"{
+                "name": "widths",
+                "testbench": "alu",
+                "parameters": {"WIDTH": ["8", "16", "32"]},
+                "defines": {"FAST": [false, true]},
+                "plusargs": {"VERBOSE": [null, "2"]}
+            }"#,
+        )
+        .unwrap();
+
+        let points = sweep.expand(&project);
+        assert_eq!(points.len(), 12);
+
+        let (point, target) = &points[0];
+        assert_eq!(target.name, "widths-0");
+        assert_eq!(point.parameters["WIDTH"], "8");
+        assert_eq!(point.defines["FAST"], Switch::Flag(false));
+        assert_eq!(target.compiler_args().unwrap(), vec!["-Ptb_alu.WIDTH=8"]);
+        assert_eq!(target.plusargs["VERBOSE"], None);
+
+        let (point, target) = &points[11];
+        assert_eq!(point.parameters["WIDTH"], "32");
+        assert_eq!(
+            target.compiler_args().unwrap(),
+            vec!["-Ptb_alu.WIDTH=32", "-DFAST"]
+        );
+        assert_eq!(target.plusargs["VERBOSE"].as_deref(), Some("2"));
+    }
+}
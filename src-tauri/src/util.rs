@@ -1,4 +1,4 @@
-use std::ffi::OsStr;
+use std::{ffi::OsStr, fs, path::PathBuf};
 
 use crate::error::Error;
 
@@ -44,3 +44,80 @@ pub fn serialize_as_string<S: serde::Serializer, T: AsRef<OsStr>>(
 ) -> Result<S::Ok, S::Error> {
     s.serialize_str(&t.as_ref().to_string_lossy())
 }
+
+/// 64-bit FNV-1a digest. Unlike the hashers of the standard library, its values do not change
+/// between Rust releases or platforms, so they can be saved and compared later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digest(u64);
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Digest {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Adds `bytes`, prefixed by their length so that consecutive values cannot be confused
+    pub fn update(&mut self, bytes: impl AsRef<[u8]>) -> &mut Self {
+        let bytes = bytes.as_ref();
+        self.write(&(bytes.len() as u64).to_le_bytes());
+        self.write(bytes);
+        self
+    }
+
+    /// Adds a list of values, prefixed by their number
+    pub fn update_all<T: AsRef<[u8]>>(&mut self, values: &[T]) -> &mut Self {
+        self.write(&(values.len() as u64).to_le_bytes());
+        for value in values {
+            self.update(value);
+        }
+        self
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Hashes the paths and contents of `files`, to detect changes of the sources
+pub fn hash_files(files: &[PathBuf]) -> Result<String, Error> {
+    let mut digest = Digest::default();
+    for file in files {
+        digest.update(file.to_string_lossy().as_bytes());
+        digest.update(fs::read(file)?);
+    }
+    Ok(digest.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stable_digest() {
+        // Reference values of FNV-1a, which must not change as they are saved
+        let mut digest = Digest::default();
+        digest.write(b"a");
+        assert_eq!(digest.finish(), "af63dc4c8601ec8c");
+
+        assert_eq!(
+            Digest::default().update("ab").update("c"),
+            Digest::default().update("ab").update("c")
+        );
+        assert_ne!(
+            Digest::default().update("ab").update("c"),
+            Digest::default().update("a").update("bc")
+        );
+        assert_ne!(
+            Digest::default().update_all(&["a", "b"]),
+            Digest::default().update_all(&["a"]).update("b")
+        );
+    }
+}
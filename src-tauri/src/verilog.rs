@@ -0,0 +1,366 @@
+//! Lightweight helpers to extract information from Verilog sources, without a full parser.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Matches a comment or a string literal, so that the comment markers inside strings are ignored
+    static ref COMMENTS: Regex =
+        Regex::new(r#"(?s)"(?:[^"\\\n]|\\.)*"|//[^\n]*|/\*.*?\*/"#).unwrap();
+    /// Matches a module definition, up to its `endmodule`
+    static ref MODULE: Regex =
+        Regex::new(r"(?s)\b(?:macro)?module\s+([A-Za-z_][A-Za-z0-9_$]*)(.*?)\bendmodule\b").unwrap();
+    /// Matches a `parameter` or `localparam` statement, with its list of assignments
+    static ref PARAMETERS: Regex =
+        Regex::new(r"\b(?:localparam|parameter)\b(?:\s+(?:integer|signed|unsigned))?\s*(?:\[[^\]]*\])?([^;]*)").unwrap();
+    /// Matches the keyword, type and range starting an item of a parameter port list
+    static ref PARAMETER_KEYWORD: Regex =
+        Regex::new(r"^\s*(?:localparam|parameter)\b(?:\s+(?:integer|signed|unsigned))?\s*(?:\[[^\]]*\])?").unwrap();
+    /// Matches the keyword and range of a net or variable declaration
+    static ref DECLARATION: Regex =
+        Regex::new(r"\b(?:reg|wire|logic|integer)\b(?:\s+signed)?\s*(?:\[[^\]]*\])?").unwrap();
+    static ref ASSIGNMENT: Regex =
+        Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_$]*)\s*=\s*(.+?)\s*$").unwrap();
+    /// Matches a sized or unsized based number, such as `4'b1010` or `'hFF`
+    static ref BASED_NUMBER: Regex =
+        Regex::new(r"^(?:\d+)?\s*'[sS]?([bBoOdDhH])\s*([0-9a-fA-F_]+)$").unwrap();
+}
+
+/// Module definition found in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    pub name: String,
+    /// Text between the module name and `endmodule`, without comments and string literals
+    pub body: String,
+}
+
+/// Replaces comments, and string literals if `strings` is set, by spaces, keeping line numbers
+/// intact
+fn blank(source: &str, strings: bool) -> String {
+    COMMENTS
+        .replace_all(source, |cap: &regex::Captures| {
+            if !strings && cap[0].starts_with('"') {
+                return cap[0].to_owned();
+            }
+            cap[0]
+                .chars()
+                .map(|c| if c == '\n' { '\n' } else { ' ' })
+                .collect::<String>()
+        })
+        .to_string()
+}
+
+/// Replaces comments by spaces, keeping line numbers intact
+pub fn strip_comments(source: &str) -> String {
+    blank(source, false)
+}
+
+/// Lists the modules defined in `source`
+pub fn modules(source: &str) -> Vec<Module> {
+    MODULE
+        .captures_iter(&blank(source, true))
+        .map(|cap| Module {
+            name: cap[1].to_owned(),
+            body: cap[2].to_owned(),
+        })
+        .collect()
+}
+
+/// Lists the `parameter` and `localparam` definitions of a module body which have a constant value
+pub fn parameters(body: &str) -> Vec<(String, u64)> {
+    let mut parameters = vec![];
+    for cap in PARAMETERS.captures_iter(body) {
+        // Assignments inside a parameter port list end with a parenthesis instead of a semicolon
+        let list = cap[1].split(')').next().unwrap_or_default();
+        for assignment in list.split(',') {
+            // In a parameter port list, each item may start with its own keyword
+            let assignment = PARAMETER_KEYWORD.replace(assignment, "");
+            if let Some(cap) = ASSIGNMENT.captures(&assignment) {
+                if let Some(value) = parse_number(&cap[2]) {
+                    parameters.push((cap[1].to_owned(), value));
+                }
+            }
+        }
+    }
+    parameters
+}
+
+/// Lists the names of the nets and variables declared in a module body
+pub fn declarations(body: &str) -> Vec<String> {
+    lazy_static! {
+        static ref NAME: Regex = Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_$]*)").unwrap();
+    }
+
+    let mut names = vec![];
+    for mat in DECLARATION.find_iter(body) {
+        let list = body[mat.end()..]
+            .split([';', ')'])
+            .next()
+            .unwrap_or_default();
+        for declaration in list.split(',') {
+            match NAME.captures(declaration) {
+                // In a port list, the next port starts with its direction
+                Some(c) if ["input", "output", "inout"].contains(&&c[1]) => break,
+                Some(c) => names.push(c[1].to_owned()),
+                None => {}
+            }
+        }
+    }
+    names
+}
+
+/// Parses a Verilog integer literal, such as `42`, `8'hFF` or `2'b1_0`
+pub fn parse_number(literal: &str) -> Option<u64> {
+    let literal = literal.trim();
+    if let Some(cap) = BASED_NUMBER.captures(literal) {
+        let radix = match cap[1].to_ascii_lowercase().as_str() {
+            "b" => 2,
+            "o" => 8,
+            "d" => 10,
+            _ => 16,
+        };
+        u64::from_str_radix(&cap[2].replace('_', ""), radix).ok()
+    } else {
+        literal.replace('_', "").parse().ok()
+    }
+}
+
+/// Whether `name` appears as a whole identifier in `text`
+pub fn contains_identifier(text: &str, name: &str) -> bool {
+    text.match_indices(name).any(|(i, _)| {
+        let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+        !text[..i].chars().next_back().is_some_and(is_identifier)
+            && !text[i + name.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_identifier)
+    })
+}
+
+/// Whether a module body, as returned by [`modules`], declares ports
+pub fn has_ports(body: &str) -> bool {
+    let mut rest = body.trim_start();
+    // Skips the parameter port list
+    if let Some(parameters) = rest.strip_prefix('#') {
+        let mut depth = 0;
+        let end = parameters.find(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0 && c == ')'
+        });
+        rest = end.map_or("", |i| parameters[i + 1..].trim_start());
+    }
+    match rest.strip_prefix('(') {
+        Some(ports) => !ports.trim_start().starts_with(')'),
+        None => false,
+    }
+}
+
+/// Whether `body` contains an instance of the module `name`
+pub fn instantiates(body: &str, name: &str) -> bool {
+    body.match_indices(name).any(|(i, _)| {
+        let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+        let rest = &body[i + name.len()..];
+        // The module name is followed by a parameter assignment or by the instance name
+        !body[..i].chars().next_back().is_some_and(is_identifier)
+            && !rest.chars().next().is_some_and(is_identifier)
+            && rest
+                .trim_start()
+                .starts_with(|c: char| c == '#' || c == '\\' || c == '_' || c.is_ascii_alphabetic())
+    })
+}
+
+/// Finds the module of `modules` instantiated as `instance` in `body`
+pub fn instance_module<'a>(
+    body: &str,
+    instance: &str,
+    modules: &'a [Module],
+) -> Option<&'a Module> {
+    lazy_static! {
+        static ref NAME: Regex = Regex::new(r"^\s*\\?([A-Za-z_][A-Za-z0-9_$]*)").unwrap();
+    }
+
+    modules.iter().find(|module| {
+        body.match_indices(&module.name).any(|(i, _)| {
+            let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+            let mut rest = &body[i + module.name.len()..];
+            if body[..i].chars().next_back().is_some_and(is_identifier)
+                || rest.chars().next().is_some_and(is_identifier)
+            {
+                return false;
+            }
+            // Skips the parameter assignments
+            if let Some(parameters) = rest.trim_start().strip_prefix('#') {
+                let mut depth = 0;
+                let end = parameters.find(|c| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0 && c == ')'
+                });
+                rest = end.map_or("", |i| &parameters[i + 1..]);
+            }
+            NAME.captures(rest).is_some_and(|cap| &cap[1] == instance)
+        })
+    })
+}
+
+/// Lists the modules of `modules` which are not instantiated by another one
+pub fn root_modules(modules: &[Module]) -> Vec<&Module> {
+    modules
+        .iter()
+        .filter(|m| {
+            !modules
+                .iter()
+                .any(|other| other.name != m.name && instantiates(&other.body, &m.name))
+        })
+        .collect()
+}
+
+/// Lists the files included by `source` with the `` `include`` directive
+pub fn includes(source: &str) -> Vec<String> {
+    lazy_static! {
+        static ref INCLUDE: Regex = Regex::new(r#"`include\s*"([^"]+)""#).unwrap();
+    }
+
+    INCLUDE
+        .captures_iter(&strip_comments(source))
+        .map(|cap| cap[1].to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("8'hFF"), Some(255));
+        assert_eq!(parse_number("2'b1_0"), Some(2));
+        assert_eq!(parse_number("'d7"), Some(7));
+        assert_eq!(parse_number("WIDTH - 1"), None);
+    }
+
+    #[test]
+    fn module_parameters() {
+        let res = modules(
+            r#"
+            module fsm #(parameter WIDTH = 8) (input clk);
+                // localparam IGNORED = 5;
+                localparam [1:0] IDLE = 2'd0,
+                                 RUN = 2'd1, DONE = 2'b10;
+                parameter integer DEPTH = WIDTH * 2;
+            endmodule
+            "#,
+        );
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].name, "fsm");
+        assert_eq!(
+            parameters(&res[0].body),
+            vec![
+                ("WIDTH".to_owned(), 8),
+                ("IDLE".to_owned(), 0),
+                ("RUN".to_owned(), 1),
+                ("DONE".to_owned(), 2)
+            ]
+        );
+
+        let res = modules(
+            "module fifo #(parameter A = 0, parameter integer B = 1, localparam [3:0] C = 4'hf, \
+             D = 3) (input clk); endmodule",
+        );
+        assert_eq!(
+            parameters(&res[0].body),
+            vec![
+                ("A".to_owned(), 0),
+                ("B".to_owned(), 1),
+                ("C".to_owned(), 15),
+                ("D".to_owned(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn module_declarations() {
+        let res = modules(
+            r#"
+            module top(input clk, input go, output reg [1:0] state, output wire done);
+                reg [7:0] a, b = 8'd0;
+                wire signed [3:0] c;
+                integer i;
+            endmodule
+            "#,
+        );
+
+        assert_eq!(
+            declarations(&res[0].body),
+            vec!["state", "done", "a", "b", "c", "i"]
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        assert!(contains_identifier("state <= IDLE", "state"));
+        assert!(!contains_identifier("next_state <= IDLE", "state"));
+    }
+
+    #[test]
+    fn roots() {
+        let res = modules(
+            r#"
+            module adder #(parameter W = (4 + 4)) (input [W-1:0] a, b, output [W:0] s);
+                assign s = a + b;
+            endmodule
+            module unused();
+            endmodule
+            module tb_adder;
+                reg [7:0] a, b;
+                wire [8:0] s;
+                adder #(.W(8)) dut (a, b, s);
+                // unused u();
+                initial $finish;
+            endmodule
+            module test;
+                initial $finish;
+            endmodule
+            module tb_mode;
+                reg test_mode, adder_done;
+                initial $display("adder done, tb_adder passed");
+            endmodule
+            "#,
+        );
+
+        assert!(has_ports(&res[0].body));
+        assert!(!has_ports(&res[1].body));
+        assert!(!has_ports(&res[2].body));
+        assert_eq!(
+            root_modules(&res)
+                .into_iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["unused", "tb_adder", "test", "tb_mode"]
+        );
+    }
+
+    #[test]
+    fn include_directives() {
+        assert_eq!(
+            includes(
+                r#"
+                `include "defs.vh"
+                // `include "old.vh"
+                `include  "common/bus.vh"
+                $display("// not a comment"); `include "last.vh"
+                "#
+            ),
+            vec!["defs.vh", "common/bus.vh", "last.vh"]
+        );
+    }
+}
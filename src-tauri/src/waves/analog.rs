@@ -0,0 +1,174 @@
+//! Min/max envelopes of numeric signals, to draw analog traces without sending every sample.
+
+use serde::{Deserialize, Serialize};
+
+use super::{dump, Source};
+use crate::{error::Error, state::AppState};
+
+/// How the bits of a signal are converted to a number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Interpretation {
+    Unsigned,
+    /// Two's complement
+    Signed,
+    Real,
+}
+
+/// Extremes of a signal over a pixel column
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bucket {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalogTrace {
+    /// Extremes over the whole time range, to scale the trace
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// One bucket per column, `None` where the value is unknown
+    pub buckets: Vec<Option<Bucket>>,
+}
+
+/// Converts a raw value of a `width`-bit variable, returning `None` if it contains unknown bits
+fn numeric_value(raw: &str, width: u32, interpretation: Interpretation) -> Option<f64> {
+    if let Some(real) = raw.strip_prefix(['r', 'R']) {
+        return real.parse().ok();
+    }
+
+    let digits = raw.strip_prefix(['b', 'B']).unwrap_or(raw);
+    let digits = &digits[digits.len().saturating_sub(64)..];
+    let value = u64::from_str_radix(digits, 2).ok()?;
+
+    match interpretation {
+        Interpretation::Signed if width > 0 && width <= 64 => {
+            let shift = 64 - width;
+            Some((((value << shift) as i64) >> shift) as f64)
+        }
+        _ => Some(value as f64),
+    }
+}
+
+/// Computes the envelope of `changes` over `[start, end)`, split in `columns` buckets
+fn envelope(changes: &[(u32, Option<f64>)], start: u32, end: u32, columns: u32) -> AnalogTrace {
+    let span = (end - start) as f64;
+    let column_start = |i: u32| start + (span * i as f64 / columns as f64) as u32;
+
+    // Value in effect at the start of the range
+    let mut idx = changes.partition_point(|c| c.0 <= start);
+    let mut current = idx.checked_sub(1).and_then(|i| changes[i].1);
+
+    let mut buckets = Vec::with_capacity(columns as usize);
+    for i in 0..columns {
+        let column_end = if i + 1 == columns {
+            end
+        } else {
+            column_start(i + 1)
+        };
+
+        let mut bucket = current.map(|v| Bucket { min: v, max: v });
+        while idx < changes.len() && changes[idx].0 < column_end {
+            current = changes[idx].1;
+            if let Some(v) = current {
+                let b = bucket.get_or_insert(Bucket { min: v, max: v });
+                b.min = b.min.min(v);
+                b.max = b.max.max(v);
+            }
+            idx += 1;
+        }
+        buckets.push(bucket);
+    }
+
+    let known = buckets.iter().flatten();
+    AnalogTrace {
+        min: known.clone().map(|b| b.min).reduce(f64::min),
+        max: known.map(|b| b.max).reduce(f64::max),
+        buckets,
+    }
+}
+
+/// Computes the envelope of a source over `[start, end)`, with one bucket per pixel column
+#[tauri::command]
+pub fn analog_envelope(
+    file: usize,
+    source: Source,
+    interpretation: Interpretation,
+    start: u32,
+    end: u32,
+    columns: u32,
+    state: AppState<'_>,
+) -> Result<AnalogTrace, Error> {
+    if end <= start || columns == 0 {
+        return Err(Error::Other(format!(
+            "Invalid analog range [{start}, {end}) with {columns} columns"
+        )));
+    }
+
+    let state = state.lock().unwrap();
+    let resolved = source.resolve(dump(&state, file)?)?;
+
+    let changes = resolved
+        .changes
+        .iter()
+        .map(|(time, raw)| (*time, numeric_value(raw, resolved.width, interpretation)))
+        .collect::<Vec<_>>();
+    Ok(envelope(&changes, start, end, columns))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            numeric_value("b1111", 4, Interpretation::Unsigned),
+            Some(15.0)
+        );
+        assert_eq!(
+            numeric_value("b1111", 4, Interpretation::Signed),
+            Some(-1.0)
+        );
+        assert_eq!(numeric_value("r-0.5", 64, Interpretation::Real), Some(-0.5));
+        assert_eq!(numeric_value("b1x", 2, Interpretation::Unsigned), None);
+    }
+
+    #[test]
+    fn buckets() {
+        let changes = [
+            (0, Some(1.0)),
+            (5, Some(3.0)),
+            (6, Some(-2.0)),
+            (12, None),
+            (25, Some(4.0)),
+        ];
+        let res = envelope(&changes, 0, 30, 3);
+
+        assert_eq!(
+            res.buckets,
+            vec![
+                Some(Bucket {
+                    min: -2.0,
+                    max: 3.0
+                }),
+                Some(Bucket {
+                    min: -2.0,
+                    max: -2.0
+                }),
+                Some(Bucket { min: 4.0, max: 4.0 }),
+            ]
+        );
+        assert_eq!((res.min, res.max), (Some(-2.0), Some(4.0)));
+    }
+
+    #[test]
+    fn unknown_column() {
+        let changes = [(0, None), (15, Some(2.0))];
+        let res = envelope(&changes, 0, 20, 2);
+
+        assert_eq!(res.buckets[0], None);
+        assert_eq!(res.buckets[1], Some(Bucket { min: 2.0, max: 2.0 }));
+    }
+}
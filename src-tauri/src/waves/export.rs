@@ -0,0 +1,330 @@
+//! Export of signals over a time range to CSV and JSON, for spreadsheets and reports.
+
+use std::{collections::HashMap, fs};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dump,
+    format::{format_value, Radix},
+    translation::{load_translations, TranslationTable},
+    Resolved, Source,
+};
+use crate::{error::Error, icarus::vcd::VCDFile, state::AppState};
+
+/// Signal exported as a column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Column {
+    #[serde(flatten)]
+    pub source: Source,
+    #[serde(default)]
+    pub radix: Radix,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Edge {
+    #[default]
+    Rising,
+    Falling,
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "format")]
+pub enum ExportFormat {
+    /// CSV with a row each time one of the signals changes
+    Changes,
+    /// CSV with a row for each edge of `clock`, holding the values sampled just before the edge
+    Clocked {
+        clock: String,
+        #[serde(default)]
+        edge: Edge,
+    },
+    /// JSON holding the value changes of each signal
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    pub columns: Vec<Column>,
+    /// Start of the exported range, the beginning of the dump by default
+    #[serde(default)]
+    pub start: Option<u32>,
+    /// End of the exported range (included), the end of the dump by default
+    #[serde(default)]
+    pub end: Option<u32>,
+    #[serde(flatten)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSignal {
+    name: String,
+    width: u32,
+    changes: Vec<(u32, String)>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExport {
+    /// Duration of a time unit, in seconds
+    timescale: f64,
+    start: u32,
+    end: u32,
+    signals: Vec<JsonSignal>,
+}
+
+/// Quotes a CSV field if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let mut row = fields
+        .into_iter()
+        .map(|f| csv_field(&f))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+/// Times of the edges of a clock, using the least significant bit of its values
+pub(super) fn clock_edges(clock: &Resolved, edge: Edge) -> Vec<u32> {
+    let mut edges = vec![];
+    let mut previous = None;
+
+    for (time, value) in &clock.changes {
+        let level = value.chars().last();
+        match (previous, level, edge) {
+            (Some('0'), Some('1'), Edge::Rising | Edge::Both)
+            | (Some('1'), Some('0'), Edge::Falling | Edge::Both) => edges.push(*time),
+            _ => {}
+        }
+        previous = level;
+    }
+
+    edges
+}
+
+/// Exports the columns of `options` from `vcd`. Translation tables are applied to the columns
+/// showing a variable of the dump.
+pub fn export(
+    vcd: &VCDFile,
+    options: &ExportOptions,
+    translations: &HashMap<String, TranslationTable>,
+) -> Result<String, Error> {
+    let start = options.start.unwrap_or(0);
+    let end = options.end.unwrap_or_else(|| vcd.end_time());
+    if start > end {
+        return Err(Error::Other(format!("Empty export range [{start}, {end}]")));
+    }
+
+    let signals = options
+        .columns
+        .iter()
+        .map(|c| c.source.resolve(vcd))
+        .collect::<Result<Vec<_>, _>>()?;
+    let format = |column: &Column, signal: &Resolved, raw: Option<&str>| {
+        let translation = match &column.source {
+            Source::Signal { path } => translations.get(path),
+            _ => None,
+        };
+        raw.map_or_else(
+            || "x".to_owned(),
+            |raw| format_value(raw, signal.width, column.radix, translation),
+        )
+    };
+    let row = |values: Vec<Option<&str>>| {
+        options
+            .columns
+            .iter()
+            .zip(&signals)
+            .zip(values)
+            .map(|((column, signal), raw)| format(column, signal, raw))
+            .collect::<Vec<_>>()
+    };
+    let names = options.columns.iter().map(|c| c.source.name());
+
+    match &options.format {
+        ExportFormat::Changes => {
+            let mut times = signals
+                .iter()
+                .flat_map(|s| s.changes.iter().map(|c| c.0))
+                .filter(|t| *t > start && *t <= end)
+                .collect::<Vec<_>>();
+            times.push(start);
+            times.sort();
+            times.dedup();
+
+            let mut out = csv_row(std::iter::once("time".to_owned()).chain(names));
+            for time in times {
+                out.push_str(&csv_row(
+                    std::iter::once(time.to_string())
+                        .chain(row(signals.iter().map(|s| s.value_at(time)).collect())),
+                ));
+            }
+            Ok(out)
+        }
+        ExportFormat::Clocked { clock, edge } => {
+            let clock = Source::Signal {
+                path: clock.clone(),
+            }
+            .resolve(vcd)?;
+
+            let mut out = csv_row(
+                ["cycle".to_owned(), "time".to_owned()]
+                    .into_iter()
+                    .chain(names),
+            );
+            let edges = clock_edges(&clock, *edge)
+                .into_iter()
+                .filter(|t| *t >= start && *t <= end);
+            for (cycle, time) in edges.enumerate() {
+                // Values are sampled as seen by the flip-flops, before the changes of the edge
+                out.push_str(&csv_row(
+                    [cycle.to_string(), time.to_string()]
+                        .into_iter()
+                        .chain(row(signals.iter().map(|s| s.value_before(time)).collect())),
+                ));
+            }
+            Ok(out)
+        }
+        ExportFormat::Json => {
+            let signals = options
+                .columns
+                .iter()
+                .zip(&signals)
+                .map(|(column, signal)| {
+                    let mut changes = vec![(start, format(column, signal, signal.value_at(start)))];
+                    changes.extend(
+                        signal
+                            .changes
+                            .iter()
+                            .filter(|c| c.0 > start && c.0 <= end)
+                            .map(|(t, v)| (*t, format(column, signal, Some(v)))),
+                    );
+                    JsonSignal {
+                        name: column.source.name(),
+                        width: signal.width,
+                        changes,
+                    }
+                })
+                .collect();
+
+            serde_json::to_string_pretty(&JsonExport {
+                timescale: vcd.timescale_seconds(),
+                start,
+                end,
+                signals,
+            })
+            .map_err(|e| Error::Other(format!("Could not serialize export: {e}")))
+        }
+    }
+}
+
+/// Exports signals of the `file`-th dump of the last simulation to `path`
+#[tauri::command]
+pub fn export_waves(
+    file: usize,
+    options: ExportOptions,
+    path: String,
+    state: AppState<'_>,
+) -> Result<(), Error> {
+    let state = state.lock().unwrap();
+    let vcd = dump(&state, file)?;
+    let translations = match state.project() {
+        Some(project) => load_translations(project, vcd)?,
+        None => HashMap::new(),
+    };
+
+    fs::write(path, export(vcd, &options, &translations)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    fn vcd() -> VCDFile {
+        test_dump(
+            "top",
+            &["wire 1 ! clk", "wire 4 @ count"],
+            r#"#0
+0!
+b0 @
+#10
+1!
+#11
+b1 @
+#20
+0!
+#30
+1!
+#31
+b1010 @
+#40
+0!
+"#,
+        )
+    }
+
+    fn options(format: ExportFormat, radix: Radix) -> ExportOptions {
+        ExportOptions {
+            columns: vec![Column {
+                source: Source::Signal {
+                    path: "top.count".to_owned(),
+                },
+                radix,
+            }],
+            start: Some(5),
+            end: None,
+            format,
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let csv = export(
+            &vcd(),
+            &options(ExportFormat::Changes, Radix::Decimal),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(csv, "time,top.count\n5,0\n11,1\n31,10\n");
+    }
+
+    #[test]
+    fn clocked() {
+        let format = ExportFormat::Clocked {
+            clock: "top.clk".to_owned(),
+            edge: Edge::Rising,
+        };
+        let csv = export(&vcd(), &options(format, Radix::Binary), &HashMap::new()).unwrap();
+        assert_eq!(csv, "cycle,time,top.count\n0,10,0000\n1,30,0001\n");
+    }
+
+    #[test]
+    fn translated() {
+        let translations = HashMap::from([(
+            "top.count".to_owned(),
+            TranslationTable::from_filter_file("1 ONE, FIRST\n"),
+        )]);
+        let csv = export(
+            &vcd(),
+            &options(ExportFormat::Changes, Radix::Hexadecimal),
+            &translations,
+        )
+        .unwrap();
+        assert_eq!(csv, "time,top.count\n5,0\n11,\"ONE, FIRST\"\n31,a\n");
+    }
+}
@@ -0,0 +1,169 @@
+//! Formatting of the raw values of a dump (e.g. `b0101`, `x`, `r1.5`) for display.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dump,
+    translation::{load_translations, TranslationTable},
+    Source,
+};
+use crate::{error::Error, state::AppState};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    /// Two's complement decimal
+    Signed,
+    #[default]
+    Hexadecimal,
+}
+
+/// Extends the binary digits of a value to `width`, following the VCD rules: values are padded
+/// with `0`, unless the leftmost digit is `x` or `z`.
+pub(super) fn extend(digits: &str, width: usize) -> String {
+    if digits.len() >= width {
+        return digits[digits.len() - width..].to_owned();
+    }
+
+    let pad = digits
+        .chars()
+        .next()
+        .filter(|c| *c == 'x' || *c == 'z')
+        .unwrap_or('0');
+    let mut extended = pad.to_string().repeat(width - digits.len());
+    extended.push_str(digits);
+    extended
+}
+
+/// Formats groups of `bits` binary digits as a single digit, marking partially unknown groups
+fn group_digits(digits: &str, bits: usize) -> String {
+    let digits = extend(digits, digits.len().div_ceil(bits) * bits);
+    digits
+        .as_bytes()
+        .chunks(bits)
+        .map(|chunk| {
+            let chunk = std::str::from_utf8(chunk).unwrap();
+            if chunk.chars().all(|c| c == 'x') {
+                'x'
+            } else if chunk.chars().all(|c| c == 'z') {
+                'z'
+            } else if let Ok(v) = u32::from_str_radix(chunk, 2) {
+                char::from_digit(v, 1 << bits).unwrap()
+            } else {
+                'X'
+            }
+        })
+        .collect()
+}
+
+/// Formats a raw value of a `width`-bit variable. Translated values are displayed with their label.
+pub fn format_value(
+    raw: &str,
+    width: u32,
+    radix: Radix,
+    translation: Option<&TranslationTable>,
+) -> String {
+    if let Some(real) = raw.strip_prefix(['r', 'R']) {
+        return real.to_owned();
+    }
+
+    let digits = raw.strip_prefix(['b', 'B']).unwrap_or(raw).to_lowercase();
+    let digits = extend(&digits, width.max(1) as usize);
+
+    if !digits.chars().all(|c| c == '0' || c == '1') {
+        return match radix {
+            Radix::Binary => digits,
+            Radix::Octal => group_digits(&digits, 3),
+            Radix::Hexadecimal => group_digits(&digits, 4),
+            Radix::Decimal | Radix::Signed if digits.chars().all(|c| c == 'z') => "z".to_owned(),
+            Radix::Decimal | Radix::Signed => "x".to_owned(),
+        };
+    }
+
+    // Only the 64 least significant bits are kept for numeric formats
+    let value = u64::from_str_radix(&digits[digits.len().saturating_sub(64)..], 2).unwrap();
+    if let Some(label) = translation.and_then(|t| t.get(value)) {
+        return label.to_owned();
+    }
+
+    match radix {
+        Radix::Binary => digits,
+        Radix::Octal => group_digits(&digits, 3),
+        Radix::Hexadecimal => group_digits(&digits, 4),
+        Radix::Decimal => value.to_string(),
+        Radix::Signed => {
+            let width = digits.len().min(64) as u32;
+            let shift = 64 - width;
+            (((value << shift) as i64) >> shift).to_string()
+        }
+    }
+}
+
+/// Formats the value changes of a source, in the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn format_signal(
+    file: usize,
+    source: Source,
+    radix: Radix,
+    state: AppState<'_>,
+) -> Result<Vec<(u32, String)>, Error> {
+    let state = state.lock().unwrap();
+    let vcd = dump(&state, file)?;
+    let resolved = source.resolve(vcd)?;
+
+    // Translation tables only apply to the variables of the dump
+    let translation = match (&source, state.project()) {
+        (Source::Signal { path }, Some(project)) => load_translations(project, vcd)?.remove(path),
+        _ => None,
+    };
+
+    Ok(resolved
+        .changes
+        .into_iter()
+        .map(|(time, raw)| {
+            (
+                time,
+                format_value(&raw, resolved.width, radix, translation.as_ref()),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn radixes() {
+        assert_eq!(format_value("b101", 8, Radix::Binary, None), "00000101");
+        assert_eq!(format_value("b11111111", 8, Radix::Hexadecimal, None), "ff");
+        assert_eq!(format_value("b1010", 6, Radix::Octal, None), "12");
+        assert_eq!(format_value("b1010", 4, Radix::Decimal, None), "10");
+        assert_eq!(format_value("b1110", 4, Radix::Signed, None), "-2");
+        assert_eq!(format_value("r2.5", 64, Radix::Decimal, None), "2.5");
+    }
+
+    #[test]
+    fn unknown_bits() {
+        assert_eq!(format_value("x", 1, Radix::Hexadecimal, None), "x");
+        assert_eq!(format_value("bx", 8, Radix::Hexadecimal, None), "xx");
+        assert_eq!(format_value("b1x0000", 8, Radix::Hexadecimal, None), "X0");
+        assert_eq!(format_value("bz", 4, Radix::Decimal, None), "z");
+    }
+
+    #[test]
+    fn translated() {
+        let table = TranslationTable::from_filter_file("0 IDLE\n1 RUN\n");
+        assert_eq!(
+            format_value("b1", 2, Radix::Hexadecimal, Some(&table)),
+            "RUN"
+        );
+        assert_eq!(
+            format_value("b10", 2, Radix::Hexadecimal, Some(&table)),
+            "2"
+        );
+    }
+}
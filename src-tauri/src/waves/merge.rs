@@ -0,0 +1,69 @@
+//! Merge of several dumps, e.g. from different runs, into a single timeline.
+
+use std::{fs, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::dump;
+use crate::{error::Error, icarus::vcd::VCDFile, state::AppState};
+
+/// Dump to merge, either from the last simulation or read from a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "origin")]
+pub enum DumpOrigin {
+    Loaded { file: usize },
+    File { path: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeInput {
+    #[serde(flatten)]
+    pub origin: DumpOrigin,
+    /// Name of the root scope holding the variables of the dump
+    pub scope: String,
+    /// Time shift, in the time unit of the dump
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// Merges dumps into a single one, which is appended to the dumps of the last simulation
+#[tauri::command]
+pub fn merge_dumps(inputs: Vec<MergeInput>, state: AppState<'_>) -> Result<VCDFile, Error> {
+    let mut state = state.lock().unwrap();
+
+    for (i, input) in inputs.iter().enumerate() {
+        if inputs[..i].iter().any(|other| other.scope == input.scope) {
+            return Err(Error::Other(format!(
+                "Several dumps are merged under {}",
+                input.scope
+            )));
+        }
+    }
+
+    let mut read = vec![];
+    for input in &inputs {
+        if let DumpOrigin::File { path } = &input.origin {
+            read.push(
+                VCDFile::from_str(&fs::read_to_string(path)?)
+                    .map_err(|e| Error::Other(format!("Could not parse {path}: {e}")))?,
+            );
+        }
+    }
+
+    let mut read = read.iter();
+    let dumps = inputs
+        .iter()
+        .map(|input| {
+            let vcd = match &input.origin {
+                DumpOrigin::Loaded { file } => dump(&state, *file)?,
+                DumpOrigin::File { .. } => read.next().unwrap(),
+            };
+            Ok((input.scope.as_str(), vcd, input.offset))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let merged = VCDFile::merge(&dumps).map_err(Error::Other)?;
+    state.waves_mut().push(merged.clone());
+    Ok(merged)
+}
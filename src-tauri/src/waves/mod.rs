@@ -0,0 +1,250 @@
+//! Queries and transformations of the simulation dumps, used by the waveform viewer.
+
+pub mod analog;
+pub mod export;
+pub mod format;
+pub mod merge;
+pub mod session;
+pub mod timing;
+pub mod translation;
+pub mod wavedrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Error, icarus::vcd::VCDFile, state::State};
+
+/// Returns the `file`-th dump of the last simulation
+pub fn dump(state: &State, file: usize) -> Result<&VCDFile, Error> {
+    state
+        .waves()
+        .get(file)
+        .ok_or_else(|| Error::Other(format!("No dump with index {file}")))
+}
+
+/// Signal displayed by a trace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Source {
+    /// Variable of the dump
+    Signal { path: String },
+    /// Single bit of a vector variable, `0` being the least significant
+    Bit { path: String, index: u32 },
+    /// Bus assembled from other sources, most significant first
+    VirtualBus { name: String, bits: Vec<Source> },
+}
+
+/// Value changes of a source, in the raw format of the dumps (e.g. `b0101`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub width: u32,
+    pub changes: Vec<(u32, String)>,
+}
+
+impl Resolved {
+    fn value_at(&self, time: u32) -> Option<&str> {
+        let idx = self.changes.partition_point(|c| c.0 <= time);
+        idx.checked_sub(1).map(|i| self.changes[i].1.as_str())
+    }
+
+    /// Value just before `time`, ignoring the changes happening at `time`
+    fn value_before(&self, time: u32) -> Option<&str> {
+        let idx = self.changes.partition_point(|c| c.0 < time);
+        idx.checked_sub(1).map(|i| self.changes[i].1.as_str())
+    }
+}
+
+/// Removes consecutive changes to the same value
+fn dedup_changes(changes: &mut Vec<(u32, String)>) {
+    changes.dedup_by(|next, prev| next.1 == prev.1);
+}
+
+impl Source {
+    /// Splits a `width`-bit vector into the sources of its bits, most significant first
+    pub fn split_bits(path: &str, width: u32) -> Vec<Source> {
+        (0..width)
+            .rev()
+            .map(|index| Source::Bit {
+                path: path.to_owned(),
+                index,
+            })
+            .collect()
+    }
+
+    /// Displayed name of the source
+    pub fn name(&self) -> String {
+        match self {
+            Source::Signal { path } => path.clone(),
+            Source::Bit { path, index } => format!("{path}[{index}]"),
+            Source::VirtualBus { name, .. } => name.clone(),
+        }
+    }
+
+    /// Whether all the variables used by the source are in `vcd`
+    pub fn exists_in(&self, vcd: &VCDFile) -> bool {
+        match self {
+            Source::Signal { path } => vcd.find_variable(path).is_some(),
+            Source::Bit { path, index } => vcd.find_variable(path).is_some_and(|v| *index < v.size),
+            Source::VirtualBus { bits, .. } => bits.iter().all(|b| b.exists_in(vcd)),
+        }
+    }
+
+    /// Computes the value changes of the source in `vcd`, sorted by time
+    pub fn resolve(&self, vcd: &VCDFile) -> Result<Resolved, Error> {
+        let variable = |path: &str| {
+            vcd.find_variable(path)
+                .ok_or_else(|| Error::Other(format!("Unknown signal {path}")))
+        };
+
+        match self {
+            Source::Signal { path } => {
+                let variable = variable(path)?;
+                Ok(Resolved {
+                    width: variable.size,
+                    changes: vcd
+                        .changes(variable.identifier)
+                        .into_iter()
+                        .map(|(t, v)| (t, v.to_owned()))
+                        .collect(),
+                })
+            }
+            Source::Bit { path, index } => {
+                let variable = variable(path)?;
+                if *index >= variable.size || variable.ty == "real" {
+                    return Err(Error::Other(format!("No bit {index} in signal {path}")));
+                }
+
+                let mut changes = vcd
+                    .changes(variable.identifier)
+                    .into_iter()
+                    .map(|(t, v)| {
+                        let digits = format::extend(
+                            v.strip_prefix(['b', 'B']).unwrap_or(v),
+                            variable.size as usize,
+                        );
+                        let bit = digits.as_bytes()[(variable.size - 1 - index) as usize];
+                        (t, (bit as char).to_string())
+                    })
+                    .collect();
+                dedup_changes(&mut changes);
+                Ok(Resolved { width: 1, changes })
+            }
+            Source::VirtualBus { bits, .. } => {
+                let bits = bits
+                    .iter()
+                    .map(|b| b.resolve(vcd))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut times = bits
+                    .iter()
+                    .flat_map(|b| b.changes.iter().map(|c| c.0))
+                    .collect::<Vec<_>>();
+                times.sort();
+                times.dedup();
+
+                let mut changes = times
+                    .into_iter()
+                    .map(|t| {
+                        let mut value = String::from("b");
+                        for bit in &bits {
+                            let width = bit.width as usize;
+                            match bit.value_at(t) {
+                                Some(v) => value.push_str(&format::extend(
+                                    v.strip_prefix(['b', 'B']).unwrap_or(v),
+                                    width,
+                                )),
+                                None => value.push_str(&"x".repeat(width)),
+                            }
+                        }
+                        (t, value)
+                    })
+                    .collect();
+                dedup_changes(&mut changes);
+
+                Ok(Resolved {
+                    width: bits.iter().map(|b| b.width).sum(),
+                    changes,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    fn vcd() -> VCDFile {
+        test_dump(
+            "top",
+            &["wire 4 ! data", "wire 1 @ a", "wire 1 # b"],
+            r#"#0
+b0 !
+0@
+1#
+#5
+b101 !
+#10
+b100 !
+1@
+"#,
+        )
+    }
+
+    #[test]
+    fn bits() {
+        let res = Source::Bit {
+            path: "top.data".to_owned(),
+            index: 2,
+        }
+        .resolve(&vcd())
+        .unwrap();
+
+        assert_eq!(res.width, 1);
+        assert_eq!(res.changes, vec![(0, "0".to_owned()), (5, "1".to_owned())]);
+        assert_eq!(
+            Source::split_bits("top.data", 2),
+            vec![
+                Source::Bit {
+                    path: "top.data".to_owned(),
+                    index: 1
+                },
+                Source::Bit {
+                    path: "top.data".to_owned(),
+                    index: 0
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn virtual_bus() {
+        let res = Source::VirtualBus {
+            name: "bus".to_owned(),
+            bits: vec![
+                Source::Signal {
+                    path: "top.a".to_owned(),
+                },
+                Source::Signal {
+                    path: "top.b".to_owned(),
+                },
+                Source::Bit {
+                    path: "top.data".to_owned(),
+                    index: 0,
+                },
+            ],
+        }
+        .resolve(&vcd())
+        .unwrap();
+
+        assert_eq!(res.width, 3);
+        assert_eq!(
+            res.changes,
+            vec![
+                (0, "b010".to_owned()),
+                (5, "b011".to_owned()),
+                (10, "b110".to_owned())
+            ]
+        );
+    }
+}
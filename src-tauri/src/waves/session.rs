@@ -0,0 +1,654 @@
+//! Waveform viewer session (displayed traces, markers, zoom), saved in the project and
+//! exchangeable with GTKWave through `.gtkw` save files.
+
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{format::Radix, Source};
+use crate::{
+    config::SESSION_FILE, error::Error, icarus::vcd::VCDFile, project::Project, state::AppState,
+};
+
+/// GTKWave trace flags, as written after `@` in save files
+mod flags {
+    pub const HEX: u32 = 0x2;
+    pub const DEC: u32 = 0x4;
+    pub const BIN: u32 = 0x8;
+    pub const OCT: u32 = 0x10;
+    pub const RJUSTIFY: u32 = 0x20;
+    pub const BLANK: u32 = 0x200;
+    pub const SIGNED: u32 = 0x400;
+    pub const CLOSED: u32 = 0x400000;
+    pub const GRP_BEGIN: u32 = 0x800000;
+    pub const GRP_END: u32 = 0x1000000;
+}
+
+/// GTKWave trace colors, indexed by their number in save files
+const COLORS: [&str; 8] = [
+    "normal", "red", "orange", "yellow", "green", "blue", "indigo", "violet",
+];
+
+/// Number of named markers (`A` to `Z`) in GTKWave
+const NAMED_MARKERS: usize = 26;
+
+/// Width in pixels of the waveform area assumed when converting GTKWave zoom levels
+const GTKW_WIDTH: u32 = 1000;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveSession {
+    /// Displayed traces and groups, in order
+    #[serde(default)]
+    pub items: Vec<SessionItem>,
+    /// Primary marker
+    #[serde(default)]
+    pub cursor: Option<u32>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// Visible time range
+    #[serde(default)]
+    pub zoom: Option<Zoom>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SessionItem {
+    Trace(Trace),
+    Group(Group),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    #[serde(flatten)]
+    pub source: Source,
+    #[serde(default)]
+    pub radix: Radix,
+    /// One of the GTKWave color names (`red`, `orange`, `yellow`, `green`, `blue`, `indigo`, `violet`)
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// Named group of items, which can be collapsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Group {
+    pub name: String,
+    #[serde(default)]
+    pub collapsed: bool,
+    #[serde(default)]
+    pub items: Vec<SessionItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Marker {
+    pub name: String,
+    pub time: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Zoom {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl WaveSession {
+    fn path(project: &Project) -> PathBuf {
+        project.project_directory.join(SESSION_FILE)
+    }
+
+    /// Loads the session of the project, or an empty one if it was never saved
+    pub fn load(project: &Project) -> Result<Self, Error> {
+        let path = Self::path(project);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| Error::Other(format!("Could not parse {}: {e}", path.display())))
+    }
+
+    pub fn save(&self, project: &Project) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Other(format!("Could not serialize session: {e}")))?;
+        fs::write(Self::path(project), content)?;
+        Ok(())
+    }
+
+    /// Drops the traces whose signals are missing from all the dumps, e.g. after a signal was renamed
+    pub fn retain_existing(&mut self, waves: &[VCDFile]) {
+        fn retain(items: &mut Vec<SessionItem>, waves: &[VCDFile]) {
+            items.retain_mut(|item| match item {
+                SessionItem::Trace(t) => waves.iter().any(|w| t.source.exists_in(w)),
+                SessionItem::Group(g) => {
+                    retain(&mut g.items, waves);
+                    true
+                }
+            });
+        }
+
+        retain(&mut self.items, waves);
+    }
+
+    /// Parses a GTKWave save file
+    pub fn from_gtkw(content: &str) -> Self {
+        let mut session = WaveSession::default();
+        let mut flags = 0;
+        let mut color = None;
+        let mut start = None;
+        let mut width = GTKW_WIDTH;
+        let mut zoom = None;
+        // Groups being parsed, the root items being at the bottom
+        let mut groups = vec![Group {
+            name: String::new(),
+            collapsed: false,
+            items: vec![],
+        }];
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("[*]") {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('-') {
+                // Blank lines and comments are ignored, only group delimiters are kept
+                if flags & flags::GRP_BEGIN != 0 {
+                    groups.push(Group {
+                        name: name.to_owned(),
+                        collapsed: flags & flags::CLOSED != 0,
+                        items: vec![],
+                    });
+                } else if flags & flags::GRP_END != 0 && groups.len() > 1 {
+                    let group = groups.pop().unwrap();
+                    groups
+                        .last_mut()
+                        .unwrap()
+                        .items
+                        .push(SessionItem::Group(group));
+                }
+            } else if let Some(directive) = line.strip_prefix('[') {
+                let (name, value) = directive.split_once(']').unwrap_or((directive, ""));
+                let value = value.trim();
+                match name {
+                    "timestart" => start = value.parse().ok(),
+                    "size" => {
+                        if let Some(w) =
+                            value.split_whitespace().next().and_then(|w| w.parse().ok())
+                        {
+                            width = w;
+                        }
+                    }
+                    "color" => {
+                        color = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|c| *c > 0 && *c < COLORS.len())
+                            .map(|c| COLORS[c].to_owned())
+                    }
+                    _ => {}
+                }
+            } else if let Some(values) = line.strip_prefix('*') {
+                let mut values = values.split_whitespace();
+                zoom = values.next().and_then(|z| z.parse::<f64>().ok());
+                session.cursor = values
+                    .next()
+                    .and_then(|m| m.parse::<i64>().ok())
+                    .and_then(|m| u32::try_from(m).ok());
+                for (i, marker) in values.take(NAMED_MARKERS).enumerate() {
+                    if let Some(time) = marker
+                        .parse::<i64>()
+                        .ok()
+                        .and_then(|m| u32::try_from(m).ok())
+                    {
+                        session.markers.push(Marker {
+                            name: ((b'A' + i as u8) as char).to_string(),
+                            time,
+                        });
+                    }
+                }
+            } else if let Some(value) = line.strip_prefix('@') {
+                flags = u32::from_str_radix(value, 16).unwrap_or(0);
+            } else if let Some(bus) = line.strip_prefix("#{") {
+                // Virtual bus: `#{name} bits...`, most significant bit first
+                let (name, bits) = bus.split_once('}').unwrap_or((bus, ""));
+                groups
+                    .last_mut()
+                    .unwrap()
+                    .items
+                    .push(SessionItem::Trace(Trace {
+                        source: Source::VirtualBus {
+                            name: name.to_owned(),
+                            bits: bits.split_whitespace().map(source_from_gtkw).collect(),
+                        },
+                        radix: radix_from_flags(flags),
+                        color: color.clone(),
+                    }));
+            } else if !line.starts_with('#') {
+                groups
+                    .last_mut()
+                    .unwrap()
+                    .items
+                    .push(SessionItem::Trace(Trace {
+                        source: source_from_gtkw(line),
+                        radix: radix_from_flags(flags),
+                        color: color.clone(),
+                    }));
+            }
+        }
+
+        // Unterminated groups are closed at the end of the file
+        while groups.len() > 1 {
+            let group = groups.pop().unwrap();
+            groups
+                .last_mut()
+                .unwrap()
+                .items
+                .push(SessionItem::Group(group));
+        }
+        session.items = groups.pop().unwrap().items;
+
+        if let (Some(start), Some(zoom)) = (start, zoom) {
+            // GTKWave displays 2^-zoom time units per pixel
+            let span = (width as f64 * 2f64.powf(-zoom)).round() as u32;
+            session.zoom = Some(Zoom {
+                start,
+                end: start.saturating_add(span),
+            });
+        }
+
+        session
+    }
+
+    /// Writes a GTKWave save file. The sizes of the signals are taken from `waves` when available.
+    pub fn to_gtkw(&self, waves: &[VCDFile]) -> String {
+        let mut out = String::from("[*]\n[*] Saved by PaluSim\n[*]\n");
+
+        if let Some(zoom) = self.zoom {
+            let span = (zoom.end.saturating_sub(zoom.start)).max(1) as f64;
+            out.push_str(&format!("[timestart] {}\n", zoom.start));
+            out.push_str(&format!("[size] {GTKW_WIDTH} 600\n"));
+            out.push_str(&format!(
+                "*{:.6} {}",
+                -(span / GTKW_WIDTH as f64).log2(),
+                self.cursor.map_or(-1, |c| c as i64)
+            ));
+        } else {
+            out.push_str(&format!(
+                "*0.000000 {}",
+                self.cursor.map_or(-1, |c| c as i64)
+            ));
+        }
+        for i in 0..NAMED_MARKERS {
+            let name = ((b'A' + i as u8) as char).to_string();
+            let time = self.markers.iter().find(|m| m.name == name);
+            out.push_str(&format!(" {}", time.map_or(-1, |m| m.time as i64)));
+        }
+        out.push('\n');
+
+        let mut writer = GtkwWriter {
+            out,
+            waves,
+            flags: None,
+            color: 0,
+        };
+        writer.write_items(&self.items);
+        writer.out
+    }
+}
+
+/// State of the trace list of a GTKWave save file being written
+struct GtkwWriter<'a> {
+    out: String,
+    waves: &'a [VCDFile],
+    /// Flags and color are only written when they change
+    flags: Option<u32>,
+    color: usize,
+}
+
+impl GtkwWriter<'_> {
+    fn write_flags(&mut self, flags: u32) {
+        if self.flags != Some(flags) {
+            self.out.push_str(&format!("@{flags:x}\n"));
+            self.flags = Some(flags);
+        }
+    }
+
+    fn write_items(&mut self, items: &[SessionItem]) {
+        for item in items {
+            match item {
+                SessionItem::Group(group) => {
+                    let closed = if group.collapsed { flags::CLOSED } else { 0 };
+                    self.write_flags(flags::GRP_BEGIN | flags::BLANK | closed);
+                    self.out.push_str(&format!("-{}\n", group.name));
+                    self.write_items(&group.items);
+                    self.write_flags(flags::GRP_END | flags::BLANK | closed);
+                    self.out.push_str(&format!("-{}\n", group.name));
+                }
+                SessionItem::Trace(trace) => {
+                    self.write_flags(flags_from_radix(trace.radix));
+
+                    let color = trace
+                        .color
+                        .as_ref()
+                        .and_then(|c| COLORS.iter().position(|n| n == c))
+                        .unwrap_or(0);
+                    if self.color != color {
+                        self.out.push_str(&format!("[color] {color}\n"));
+                        self.color = color;
+                    }
+
+                    let line = match &trace.source {
+                        Source::VirtualBus { name, bits } => format!(
+                            "#{{{name}}} {}",
+                            bits.iter()
+                                .map(|b| self.source_name(b))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        ),
+                        source => self.source_name(source),
+                    };
+                    self.out.push_str(&line);
+                    self.out.push('\n');
+                }
+            }
+        }
+    }
+
+    /// Name of a source as written by GTKWave, e.g. `top.data[7:0]` or `(0)top.data[7:0]` for its
+    /// most significant bit
+    fn source_name(&self, source: &Source) -> String {
+        let size = |path: &str| {
+            self.waves
+                .iter()
+                .find_map(|w| w.find_variable(path))
+                .map(|v| v.size)
+        };
+
+        match source {
+            Source::Signal { path } => match size(path) {
+                Some(size) if size > 1 => format!("{path}[{}:0]", size - 1),
+                _ => path.clone(),
+            },
+            // Bits outside of the vector, e.g. after it was narrowed, are written on their own
+            Source::Bit { path, index } => {
+                match size(path).and_then(|size| Some((size.checked_sub(1 + index)?, size - 1))) {
+                    Some((position, msb)) => format!("({position}){path}[{msb}:0]"),
+                    None => format!("(0){path}[{index}:{index}]"),
+                }
+            }
+            // Nested virtual buses cannot be represented, their bits are inlined instead
+            Source::VirtualBus { bits, .. } => bits
+                .iter()
+                .map(|b| self.source_name(b))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Parses a trace name written by GTKWave, which may be a bit extracted from a vector
+/// (`(pos)name[msb:lsb]`, `pos` counting from the most significant bit)
+fn source_from_gtkw(name: &str) -> Source {
+    let path = strip_range(name);
+
+    if let Some((position, path)) = path
+        .strip_prefix('(')
+        .and_then(|p| p.split_once(')'))
+        .and_then(|(pos, path)| Some((pos.parse::<u32>().ok()?, path)))
+    {
+        let msb = name[name.rfind('[').unwrap_or(name.len())..]
+            .trim_matches(['[', ']'])
+            .split(':')
+            .next()
+            .and_then(|m| m.parse::<u32>().ok())
+            .unwrap_or(0);
+        return Source::Bit {
+            path: path.to_owned(),
+            index: msb.saturating_sub(position),
+        };
+    }
+
+    Source::Signal {
+        path: path.to_owned(),
+    }
+}
+
+/// Removes the `[msb:lsb]` suffix GTKWave appends to vector names
+fn strip_range(name: &str) -> &str {
+    match name.rfind('[') {
+        Some(i) if name.ends_with(']') && name[i..].contains(':') => &name[..i],
+        _ => name,
+    }
+}
+
+fn radix_from_flags(flags: u32) -> Radix {
+    if flags & flags::BIN != 0 {
+        Radix::Binary
+    } else if flags & flags::OCT != 0 {
+        Radix::Octal
+    } else if flags & flags::DEC != 0 && flags & flags::SIGNED != 0 {
+        Radix::Signed
+    } else if flags & flags::DEC != 0 {
+        Radix::Decimal
+    } else {
+        Radix::Hexadecimal
+    }
+}
+
+fn flags_from_radix(radix: Radix) -> u32 {
+    flags::RJUSTIFY
+        | match radix {
+            Radix::Binary => flags::BIN,
+            Radix::Octal => flags::OCT,
+            Radix::Decimal => flags::DEC,
+            Radix::Signed => flags::DEC | flags::SIGNED,
+            Radix::Hexadecimal => flags::HEX,
+        }
+}
+
+#[tauri::command]
+pub fn get_session(state: AppState<'_>) -> Result<WaveSession, Error> {
+    let state = state.lock().unwrap();
+    WaveSession::load(state.project().ok_or(Error::NoProject)?)
+}
+
+#[tauri::command]
+pub fn set_session(session: WaveSession, state: AppState<'_>) -> Result<(), Error> {
+    let state = state.lock().unwrap();
+    session.save(state.project().ok_or(Error::NoProject)?)
+}
+
+/// Bit-blasts a vector of the `file`-th dump into a group holding a trace for each of its bits
+#[tauri::command]
+pub fn split_bus(file: usize, path: String, state: AppState<'_>) -> Result<SessionItem, Error> {
+    let state = state.lock().unwrap();
+    let variable = super::dump(&state, file)?
+        .find_variable(&path)
+        .ok_or_else(|| Error::Other(format!("No signal named {path}")))?;
+
+    Ok(SessionItem::Group(Group {
+        items: Source::split_bits(&path, variable.size)
+            .into_iter()
+            .map(|source| {
+                SessionItem::Trace(Trace {
+                    source,
+                    radix: Radix::Binary,
+                    color: None,
+                })
+            })
+            .collect(),
+        name: path,
+        collapsed: false,
+    }))
+}
+
+/// Replaces the session of the project by the content of a GTKWave save file
+#[tauri::command]
+pub fn import_gtkw(path: String, state: AppState<'_>) -> Result<WaveSession, Error> {
+    let state = state.lock().unwrap();
+    let session = WaveSession::from_gtkw(&fs::read_to_string(path)?);
+    session.save(state.project().ok_or(Error::NoProject)?)?;
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn export_gtkw(path: String, state: AppState<'_>) -> Result<(), Error> {
+    let state = state.lock().unwrap();
+    let mut session = WaveSession::load(state.project().ok_or(Error::NoProject)?)?;
+    if !state.waves().is_empty() {
+        session.retain_existing(state.waves());
+    }
+    fs::write(path, session.to_gtkw(state.waves()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    const GTKW: &str = r#"[*]
+[*] GTKWave Analyzer v3.3.104 (w)1999-2020 BSI
+[*] Thu Sep 14 10:12:43 2023
+[*]
+[dumpfile] "/home/user/project/out/test.vcd"
+[timestart] 100
+[size] 1000 600
+[pos] -1 -1
+*-2.000000 150 -1 300 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1
+[treeopen] test.
+[sst_width] 200
+@28
+test.clk
+@22
+[color] 3
+test.value[7:0]
+@424
+test.c1.count[3:0]
+@800200
+-Control
+@28
+(0)test.value[7:0]
+(7)test.value[7:0]
+@c00200
+-Nested
+@22
+#{ctl} test.clk (1)test.c1.count[3:0]
+@1400200
+-Nested
+@1000200
+-Control
+"#;
+
+    fn trace(source: Source, radix: Radix, color: Option<&str>) -> SessionItem {
+        SessionItem::Trace(Trace {
+            source,
+            radix,
+            color: color.map(str::to_owned),
+        })
+    }
+
+    fn signal(path: &str) -> Source {
+        Source::Signal {
+            path: path.to_owned(),
+        }
+    }
+
+    fn bit(path: &str, index: u32) -> Source {
+        Source::Bit {
+            path: path.to_owned(),
+            index,
+        }
+    }
+
+    #[test]
+    fn gtkw_import() {
+        let session = WaveSession::from_gtkw(GTKW);
+
+        assert_eq!(session.cursor, Some(150));
+        assert_eq!(
+            session.markers,
+            vec![Marker {
+                name: "B".to_owned(),
+                time: 300
+            }]
+        );
+        assert_eq!(
+            session.zoom,
+            Some(Zoom {
+                start: 100,
+                end: 4100
+            })
+        );
+        assert_eq!(
+            session.items,
+            vec![
+                trace(signal("test.clk"), Radix::Binary, None),
+                trace(signal("test.value"), Radix::Hexadecimal, Some("yellow")),
+                trace(signal("test.c1.count"), Radix::Signed, Some("yellow")),
+                SessionItem::Group(Group {
+                    name: "Control".to_owned(),
+                    collapsed: false,
+                    items: vec![
+                        trace(bit("test.value", 7), Radix::Binary, Some("yellow")),
+                        trace(bit("test.value", 0), Radix::Binary, Some("yellow")),
+                        SessionItem::Group(Group {
+                            name: "Nested".to_owned(),
+                            collapsed: true,
+                            items: vec![trace(
+                                Source::VirtualBus {
+                                    name: "ctl".to_owned(),
+                                    bits: vec![signal("test.clk"), bit("test.c1.count", 2)],
+                                },
+                                Radix::Hexadecimal,
+                                Some("yellow")
+                            )]
+                        })
+                    ]
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn gtkw_round_trip() {
+        let session = WaveSession::from_gtkw(GTKW);
+        assert_eq!(WaveSession::from_gtkw(&session.to_gtkw(&[])), session);
+    }
+
+    #[test]
+    fn gtkw_bit_out_of_range() {
+        let vcd = test_dump("top", &["wire 4 ! data"], "#0\nb0 !\n");
+        let bit = |index| Source::Bit {
+            path: "top.data".to_owned(),
+            index,
+        };
+        let mut session = WaveSession {
+            items: [1, 6]
+                .into_iter()
+                .map(|index| {
+                    SessionItem::Trace(Trace {
+                        source: bit(index),
+                        radix: Radix::default(),
+                        color: None,
+                    })
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let writer = GtkwWriter {
+            out: String::new(),
+            waves: std::slice::from_ref(&vcd),
+            flags: None,
+            color: 0,
+        };
+        assert_eq!(writer.source_name(&bit(1)), "(2)top.data[3:0]");
+        assert_eq!(writer.source_name(&bit(6)), "(0)top.data[6:6]");
+
+        session.retain_existing(std::slice::from_ref(&vcd));
+        assert_eq!(session.items.len(), 1);
+    }
+}
@@ -0,0 +1,244 @@
+//! Setup/hold checks of data signals against a clock, and checks of the clock itself (period
+//! jitter, missing edges), for gate-level and delay-annotated simulations.
+
+use serde::{Deserialize, Serialize};
+
+use super::dump;
+use crate::{decoders::Signal, error::Error, icarus::vcd::VCDFile, state::AppState};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingConfig {
+    pub clock: String,
+    /// Use the falling edges of the clock as active edges instead of the rising ones
+    #[serde(default)]
+    pub falling_edge: bool,
+    /// Paths of the data signals sampled by the clock
+    #[serde(default)]
+    pub data: Vec<String>,
+    /// Time before an active edge during which the data must be stable
+    #[serde(default)]
+    pub setup: u32,
+    /// Time after an active edge during which the data must be stable
+    #[serde(default)]
+    pub hold: u32,
+    /// Expected clock period, the median period of the clock by default
+    #[serde(default)]
+    pub period: Option<u32>,
+    /// Largest accepted difference between a period and the expected one
+    #[serde(default)]
+    pub jitter_tolerance: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ViolationKind {
+    /// `signal` changed `margin` time units before the active edge at `edge`
+    Setup {
+        signal: String,
+        edge: u32,
+        margin: u32,
+    },
+    /// `signal` changed `margin` time units after the active edge at `edge`
+    Hold {
+        signal: String,
+        edge: u32,
+        margin: u32,
+    },
+    /// The period ending at the violation is too far from the expected one
+    Jitter { period: u32 },
+    /// `count` active edges are missing after the edge at `previous`
+    MissingEdges { previous: u32, count: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub time: u32,
+    #[serde(flatten)]
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingReport {
+    /// Number of active edges of the clock
+    pub edges: usize,
+    /// Expected period, `None` if the clock has less than two active edges
+    pub period: Option<u32>,
+    pub min_period: Option<u32>,
+    pub max_period: Option<u32>,
+    /// Violations, sorted by time
+    pub violations: Vec<Violation>,
+}
+
+/// Checks the clock and data signals of `vcd`
+pub fn check_timing(vcd: &VCDFile, config: &TimingConfig) -> Result<TimingReport, Error> {
+    let edges = Signal::from_vcd(vcd, &config.clock)?
+        .edges()
+        .filter(|(_, level)| *level != config.falling_edge)
+        .map(|(time, _)| time)
+        .collect::<Vec<_>>();
+    let periods = edges.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+    let period = config.period.or_else(|| {
+        let mut sorted = periods.clone();
+        sorted.sort();
+        sorted.get(sorted.len() / 2).copied()
+    });
+    let mut violations = vec![];
+
+    if let Some(period) = period.filter(|p| *p > 0) {
+        for (window, actual) in edges.windows(2).zip(&periods) {
+            // Periods of about twice the expected one or more are missing edges rather than jitter
+            let missing = (actual + period / 2) / period;
+            if missing >= 2 {
+                violations.push(Violation {
+                    time: window[0] + period,
+                    kind: ViolationKind::MissingEdges {
+                        previous: window[0],
+                        count: missing - 1,
+                    },
+                });
+            } else if actual.abs_diff(period) > config.jitter_tolerance {
+                violations.push(Violation {
+                    time: window[1],
+                    kind: ViolationKind::Jitter { period: *actual },
+                });
+            }
+        }
+    }
+
+    for path in &config.data {
+        let signal = Signal::from_vcd(vcd, path)?;
+        // The first value of the signal is its initial value, not a change
+        for change in signal.times().skip(1) {
+            let next = edges.partition_point(|e| *e <= change);
+            if let Some(edge) = edges.get(next).filter(|e| *e - change < config.setup) {
+                violations.push(Violation {
+                    time: change,
+                    kind: ViolationKind::Setup {
+                        signal: path.clone(),
+                        edge: *edge,
+                        margin: edge - change,
+                    },
+                });
+            }
+            if let Some(edge) = next
+                .checked_sub(1)
+                .map(|i| edges[i])
+                .filter(|e| change - e < config.hold)
+            {
+                violations.push(Violation {
+                    time: change,
+                    kind: ViolationKind::Hold {
+                        signal: path.clone(),
+                        edge,
+                        margin: change - edge,
+                    },
+                });
+            }
+        }
+    }
+
+    violations.sort_by_key(|v| v.time);
+    Ok(TimingReport {
+        edges: edges.len(),
+        period,
+        min_period: periods.iter().min().copied(),
+        max_period: periods.iter().max().copied(),
+        violations,
+    })
+}
+
+/// Runs timing checks on the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn timing_checks(
+    file: usize,
+    config: TimingConfig,
+    state: AppState<'_>,
+) -> Result<TimingReport, Error> {
+    let state = state.lock().unwrap();
+    check_timing(dump(&state, file)?, &config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::icarus::vcd::test_dump;
+
+    fn vcd() -> VCDFile {
+        let mut dump = "#0\n0!\nb0 @\n".to_owned();
+
+        // Rising edges every 10, without the one at 60 and with the one at 90 late by 1
+        let mut changes = vec![(8, "b1 @"), (21, "b10 @"), (35, "b11 @")];
+        for rise in [10, 20, 30, 40, 50, 70, 80, 91, 100] {
+            changes.push((rise, "1!"));
+            changes.push((rise + 5, "0!"));
+        }
+        changes.sort_by_key(|c| c.0);
+        for (time, change) in changes {
+            dump.push_str(&format!("#{time}\n{change}\n"));
+        }
+
+        test_dump("top", &["wire 1 ! clk", "wire 8 @ data"], &dump)
+    }
+
+    #[test]
+    fn setup_hold_and_clock() {
+        let report = check_timing(
+            &vcd(),
+            &TimingConfig {
+                clock: "top.clk".to_owned(),
+                falling_edge: false,
+                data: vec!["top.data".to_owned()],
+                setup: 3,
+                hold: 2,
+                period: None,
+                jitter_tolerance: 0,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.edges, 9);
+        assert_eq!(report.period, Some(10));
+        assert_eq!(report.min_period, Some(9));
+        assert_eq!(report.max_period, Some(20));
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation {
+                    time: 8,
+                    kind: ViolationKind::Setup {
+                        signal: "top.data".to_owned(),
+                        edge: 10,
+                        margin: 2
+                    }
+                },
+                Violation {
+                    time: 21,
+                    kind: ViolationKind::Hold {
+                        signal: "top.data".to_owned(),
+                        edge: 20,
+                        margin: 1
+                    }
+                },
+                Violation {
+                    time: 60,
+                    kind: ViolationKind::MissingEdges {
+                        previous: 50,
+                        count: 1
+                    }
+                },
+                Violation {
+                    time: 91,
+                    kind: ViolationKind::Jitter { period: 11 }
+                },
+                Violation {
+                    time: 100,
+                    kind: ViolationKind::Jitter { period: 9 }
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Translation tables, displaying the values of a signal with a label (e.g. the states of a FSM).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::dump;
+use crate::{
+    error::Error,
+    icarus::vcd::VCDFile,
+    project::Project,
+    state::AppState,
+    verilog::{self, Module},
+};
+
+/// Mapping of values to labels
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranslationTable(BTreeMap<u64, String>);
+
+impl TranslationTable {
+    /// Parses a GTKWave translate filter file. Each line contains a hexadecimal value followed by
+    /// its label, which may be prefixed by a `?color?` marker. Lines starting with `#` are ignored.
+    pub fn from_filter_file(content: &str) -> Self {
+        let mut table = BTreeMap::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (value, label) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let label = label.trim();
+            let label = match label.strip_prefix('?').and_then(|l| l.split_once('?')) {
+                Some((_color, label)) => label,
+                None => label,
+            };
+
+            match u64::from_str_radix(value, 16) {
+                Ok(value) => {
+                    table.insert(value, label.to_owned());
+                }
+                Err(_) => tracing::warn!("Invalid value in translate filter file: {line}"),
+            }
+        }
+        Self(table)
+    }
+
+    /// Writes the table as a GTKWave translate filter file
+    pub fn to_filter_file(&self) -> String {
+        self.0
+            .iter()
+            .map(|(value, label)| format!("{value:X} {label}\n"))
+            .collect()
+    }
+
+    pub fn get(&self, value: u64) -> Option<&str> {
+        self.0.get(&value).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Builds a translation table for the signal `name` from the constants of the module declaring it.
+///
+/// Only the `parameter` and `localparam` used in statements mentioning the signal are kept, which
+/// picks the states of a FSM register (e.g. `state <= IDLE;` or `case (state)`).
+fn table_from_module(module: &Module, name: &str) -> TranslationTable {
+    let mut table = BTreeMap::new();
+    if !verilog::declarations(&module.body)
+        .iter()
+        .any(|d| d == name)
+    {
+        return TranslationTable(table);
+    }
+
+    let statements = module.body.split(';').collect::<Vec<_>>();
+    let parameters = verilog::parameters(&module.body);
+    for statement in statements.iter().filter(|s| {
+        verilog::contains_identifier(s, name)
+            && !verilog::contains_identifier(s, "localparam")
+            && !verilog::contains_identifier(s, "parameter")
+    }) {
+        for (parameter, value) in &parameters {
+            if verilog::contains_identifier(statement, parameter) {
+                table.entry(*value).or_insert_with(|| parameter.clone());
+            }
+        }
+    }
+
+    TranslationTable(table)
+}
+
+/// Finds the module of a scope of a dump (e.g. `tb.dut`), following the instances from the root
+/// module. Scopes which are not module instances, such as named blocks, cannot be resolved.
+fn scope_module<'a>(modules: &'a [Module], scope: &str) -> Option<&'a Module> {
+    let mut instances = scope.split('.');
+    let root = instances.next()?;
+    let mut module = modules.iter().find(|m| m.name == root)?;
+    for instance in instances {
+        module = verilog::instance_module(&module.body, instance, modules)?;
+    }
+    Some(module)
+}
+
+/// Builds the translation table of the signal at `path` from the module of its scope, empty if the
+/// scope cannot be resolved
+fn table_for_signal(modules: &[Module], path: &str, reference: &str) -> TranslationTable {
+    path.strip_suffix(reference)
+        .and_then(|scope| scope.strip_suffix('.'))
+        .and_then(|scope| scope_module(modules, scope))
+        .map(|module| table_from_module(module, reference))
+        .unwrap_or_default()
+}
+
+/// Loads the translation tables of the signals of `vcd`, indexed by signal path.
+///
+/// Tables are populated from the constants of the project sources, then overridden by the filter
+/// files declared in the project.
+pub fn load_translations(
+    project: &Project,
+    vcd: &VCDFile,
+) -> Result<HashMap<String, TranslationTable>, Error> {
+    let mut modules = vec![];
+    for file in project.source_files()? {
+        modules.append(&mut verilog::modules(&fs::read_to_string(file)?));
+    }
+
+    let mut translations = HashMap::new();
+    for (path, variable) in vcd.variables() {
+        let table = table_for_signal(&modules, &path, &variable.reference);
+        if !table.is_empty() {
+            translations.insert(path, table);
+        }
+    }
+
+    for (path, filter_file) in &project.translations {
+        let content = fs::read_to_string(project.project_directory.join(filter_file))?;
+        translations.insert(path.clone(), TranslationTable::from_filter_file(&content));
+    }
+
+    Ok(translations)
+}
+
+/// Returns the translation tables of the signals of the `file`-th dump of the last simulation
+#[tauri::command]
+pub fn get_translations(
+    file: usize,
+    state: AppState<'_>,
+) -> Result<HashMap<String, TranslationTable>, Error> {
+    let state = state.lock().unwrap();
+    let project = state.project().ok_or(Error::NoProject)?;
+    load_translations(project, dump(&state, file)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filter_file() {
+        let table = TranslationTable::from_filter_file(
+            "# FSM states\n0 IDLE\n1 ?DarkBlue?RUN\nA  Ten items\n",
+        );
+
+        assert_eq!(table.get(0), Some("IDLE"));
+        assert_eq!(table.get(1), Some("RUN"));
+        assert_eq!(table.get(10), Some("Ten items"));
+        assert_eq!(table.to_filter_file(), "0 IDLE\n1 RUN\nA Ten items\n");
+    }
+
+    #[test]
+    fn fsm_states() {
+        let modules = verilog::modules(
+            r#"
+            module fsm(input clk, input go, output reg [1:0] state);
+                localparam IDLE = 2'd0, RUN = 2'd1, DONE = 2'd2;
+                localparam TIMEOUT = 100;
+                always @(posedge clk)
+                    case (state)
+                        IDLE: if (go) state <= RUN;
+                        RUN: state <= DONE;
+                        DONE: state <= IDLE;
+                    endcase
+            endmodule
+            "#,
+        );
+
+        let table = table_from_module(&modules[0], "state");
+        assert_eq!(table.get(0), Some("IDLE"));
+        assert_eq!(table.get(1), Some("RUN"));
+        assert_eq!(table.get(2), Some("DONE"));
+        assert_eq!(table.get(100), None);
+
+        assert!(table_from_module(&modules[0], "go").is_empty());
+    }
+
+    #[test]
+    fn states_of_scope() {
+        let modules = verilog::modules(
+            r#"
+            module uart_rx(input clk, output reg [1:0] state);
+                localparam IDLE = 2'd0, DATA = 2'd1;
+                always @(posedge clk) if (state == IDLE) state <= DATA;
+            endmodule
+            module arbiter #(parameter N = 2) (input clk, output reg [1:0] state);
+                localparam RESET = 2'd0, GRANT = 2'd2;
+                always @(posedge clk) if (state == RESET) state <= GRANT;
+            endmodule
+            module tb;
+                reg clk;
+                wire [1:0] s1, s2;
+                uart_rx rx(clk, s1);
+                arbiter #(.N(4)) arb (clk, s2);
+                begin : stimulus
+                    reg [1:0] state;
+                end
+            endmodule
+            "#,
+        );
+
+        let rx = table_for_signal(&modules, "tb.rx.state", "state");
+        assert_eq!(rx.get(0), Some("IDLE"));
+        assert_eq!(rx.get(1), Some("DATA"));
+        assert_eq!(rx.get(2), None);
+
+        let arb = table_for_signal(&modules, "tb.arb.state", "state");
+        assert_eq!(arb.get(0), Some("RESET"));
+        assert_eq!(arb.get(2), Some("GRANT"));
+        assert_eq!(arb.get(1), None);
+
+        assert!(table_for_signal(&modules, "tb.stimulus.state", "state").is_empty());
+    }
+}
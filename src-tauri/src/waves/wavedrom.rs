@@ -0,0 +1,773 @@
+//! Conversion between dumps and WaveDrom timing diagrams (WaveJSON), and rendering of the diagrams
+//! to standalone SVG files.
+
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use super::{
+    dump,
+    export::{clock_edges, Column, Edge},
+    format::format_value,
+    translation::{load_translations, TranslationTable},
+    Source,
+};
+use crate::{error::Error, icarus::vcd::VCDFile, state::AppState};
+
+/// WaveDrom diagram, see <https://wavedrom.com/tutorial.html>
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveJson {
+    pub signal: Vec<Lane>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<Head>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Config>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Head {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Number of the first cycle, displays the cycle numbers when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hscale: Option<u32>,
+}
+
+/// Signal of a diagram. Lanes without a wave are used as spacers.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveSignal {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub wave: String,
+    /// Labels of the data cells (`=`, `2` to `9`), in order
+    #[serde(
+        default,
+        deserialize_with = "deserialize_data",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub data: Vec<String>,
+    /// Number of cycles of each character of the wave
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<u32>,
+}
+
+/// Data labels can be given as a list or as a space separated string
+fn deserialize_data<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    Ok(match Value::deserialize(d)? {
+        Value::String(s) => s.split_whitespace().map(str::to_owned).collect(),
+        Value::Array(values) => values
+            .into_iter()
+            .map(|v| match v {
+                Value::String(s) => s,
+                v => v.to_string(),
+            })
+            .collect(),
+        _ => vec![],
+    })
+}
+
+/// Lane of a diagram, groups being written as `["name", lanes...]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lane {
+    Signal(WaveSignal),
+    Group { name: String, lanes: Vec<Lane> },
+}
+
+impl Serialize for Lane {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Lane::Signal(signal) => signal.serialize(s),
+            Lane::Group { name, lanes } => {
+                let mut items = vec![Value::String(name.clone())];
+                for lane in lanes {
+                    items.push(serde_json::to_value(lane).map_err(serde::ser::Error::custom)?);
+                }
+                items.serialize(s)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Lane {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        fn from_value(value: Value) -> Result<Lane, serde_json::Error> {
+            match value {
+                Value::Array(items) => {
+                    let mut items = items.into_iter().peekable();
+                    let name = match items.peek() {
+                        Some(Value::String(name)) => name.clone(),
+                        _ => String::new(),
+                    };
+                    Ok(Lane::Group {
+                        name,
+                        lanes: items
+                            .filter(|i| !i.is_string())
+                            .map(from_value)
+                            .collect::<Result<_, _>>()?,
+                    })
+                }
+                value => Ok(Lane::Signal(serde_json::from_value(value)?)),
+            }
+        }
+
+        from_value(Value::deserialize(d)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl WaveJson {
+    /// Parses a WaveJSON file, which is usually written in JSON5 (unquoted keys, single quotes,
+    /// comments and trailing commas)
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        serde_json::from_str(&json5_to_json(content))
+            .map_err(|e| Error::Other(format!("Invalid WaveJSON: {e}")))
+    }
+
+    /// Signals of the diagram with their depth in the groups, group names being prefixed to the
+    /// signal names
+    fn signals(&self) -> Vec<(usize, String, &WaveSignal)> {
+        fn walk<'a>(
+            lanes: &'a [Lane],
+            prefix: &str,
+            depth: usize,
+            out: &mut Vec<(usize, String, &'a WaveSignal)>,
+        ) {
+            for lane in lanes {
+                match lane {
+                    Lane::Signal(signal) => {
+                        out.push((depth, format!("{prefix}{}", signal.name), signal))
+                    }
+                    Lane::Group { name, lanes } => {
+                        walk(lanes, &format!("{prefix}{name}."), depth + 1, out)
+                    }
+                }
+            }
+        }
+
+        let mut out = vec![];
+        walk(&self.signal, "", 0, &mut out);
+        out
+    }
+}
+
+/// Converts the relaxed JSON syntax used by WaveDrom to plain JSON
+fn json5_to_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                out.push('"');
+                while let Some(s) = chars.next() {
+                    match s {
+                        '\\' => match chars.next() {
+                            Some('\'') => out.push('\''),
+                            Some(e) => {
+                                out.push('\\');
+                                out.push(e);
+                            }
+                            None => {}
+                        },
+                        s if s == c => break,
+                        '"' => out.push_str("\\\""),
+                        s => out.push(s),
+                    }
+                }
+                out.push('"');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for s in chars.by_ref() {
+                    if s == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for s in chars.by_ref() {
+                    if previous == '*' && s == '/' {
+                        break;
+                    }
+                    previous = s;
+                }
+            }
+            c if c.is_ascii_digit() => {
+                out.push(c);
+                while let Some(&n) = chars.peek() {
+                    if !(n.is_ascii_alphanumeric() || n == '.') {
+                        break;
+                    }
+                    out.push(n);
+                    chars.next();
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut ident = c.to_string();
+                while let Some(&n) = chars.peek() {
+                    if !(n.is_alphanumeric() || n == '_' || n == '$') {
+                        break;
+                    }
+                    ident.push(n);
+                    chars.next();
+                }
+                if matches!(ident.as_str(), "true" | "false" | "null") {
+                    out.push_str(&ident);
+                } else {
+                    out.push_str(&format!("\"{ident}\""));
+                }
+            }
+            ']' | '}' => {
+                // Trailing commas
+                let len = out.trim_end().len();
+                if out[..len].ends_with(',') {
+                    out.truncate(len - 1);
+                }
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Window of a dump converted to a diagram, cycles being counted from the rising edges of `clock`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveWindow {
+    pub columns: Vec<Column>,
+    pub clock: String,
+    #[serde(default)]
+    pub first_cycle: usize,
+    /// Last cycle (included), the last complete cycle of the dump by default
+    #[serde(default)]
+    pub last_cycle: Option<usize>,
+}
+
+/// Converts a window of `vcd` to a diagram. The values displayed for each cycle are the ones found
+/// in the middle of the cycle, translated for the columns showing a variable of the dump.
+pub fn from_vcd(
+    vcd: &VCDFile,
+    window: &WaveWindow,
+    translations: &HashMap<String, TranslationTable>,
+) -> Result<WaveJson, Error> {
+    let clock = Source::Signal {
+        path: window.clock.clone(),
+    }
+    .resolve(vcd)?;
+    let edges = clock_edges(&clock, Edge::Rising);
+
+    // The last cycle ends at the next rising edge, or after the same duration as the previous one
+    let cycles = edges.len().saturating_sub(1);
+    let last = window
+        .last_cycle
+        .map_or(cycles, |l| (l + 1).min(edges.len()));
+    if window.first_cycle >= last {
+        return Err(Error::Other(format!(
+            "No complete clock cycle in [{}, {}]",
+            window.first_cycle,
+            last as i64 - 1
+        )));
+    }
+    let middles = (window.first_cycle..last)
+        .map(|i| match edges.get(i + 1) {
+            Some(next) => edges[i] + (next - edges[i]) / 2,
+            None if i > 0 => edges[i] + (edges[i] - edges[i - 1]) / 2,
+            None => edges[i],
+        })
+        .collect::<Vec<_>>();
+
+    let mut lanes = vec![Lane::Signal(WaveSignal {
+        name: window.clock.clone(),
+        wave: format!("p{}", ".".repeat(middles.len() - 1)),
+        ..Default::default()
+    })];
+
+    for column in &window.columns {
+        let signal = column.source.resolve(vcd)?;
+        let translation = match &column.source {
+            Source::Signal { path } => translations.get(path),
+            _ => None,
+        };
+        let mut wave = String::new();
+        let mut data = vec![];
+        let mut previous = None;
+
+        for middle in &middles {
+            let value = signal.value_at(*middle).map_or_else(
+                || "x".to_owned(),
+                |raw| format_value(raw, signal.width, column.radix, translation),
+            );
+            if previous.as_ref() == Some(&value) {
+                wave.push('.');
+                continue;
+            }
+
+            if value.chars().all(|c| c == 'x' || c == 'X') {
+                wave.push('x');
+            } else if value.chars().all(|c| c == 'z') {
+                wave.push('z');
+            } else if signal.width == 1 && (value == "0" || value == "1") {
+                wave.push_str(&value);
+            } else {
+                wave.push('=');
+                data.push(value.clone());
+            }
+            previous = Some(value);
+        }
+
+        lanes.push(Lane::Signal(WaveSignal {
+            name: column.source.name(),
+            wave,
+            data,
+            period: None,
+        }));
+    }
+
+    Ok(WaveJson {
+        signal: lanes,
+        head: Some(Head {
+            text: None,
+            tick: Some(window.first_cycle as u32),
+        }),
+        config: None,
+    })
+}
+
+/// Waveform described by a diagram, e.g. the stimulus expected by a lab handout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpectedWave {
+    pub name: String,
+    /// Value changes, as levels (`0`, `1`, `x`, `z`) or data labels
+    pub changes: Vec<(u32, String)>,
+}
+
+/// State of a cycle of a lane
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell<'a> {
+    Level(bool),
+    Unknown,
+    HighZ,
+    Data(&'a str),
+    /// Clock starting with a rising (`true`) or falling edge
+    Clock(bool),
+}
+
+/// Cycles of a lane, with whether each of them starts a new state (i.e. is not a `.`)
+fn cells(signal: &WaveSignal) -> Vec<(Cell<'_>, bool)> {
+    let period = signal.period.unwrap_or(1).max(1) as usize;
+    let mut data = signal.data.iter();
+    let mut cells = vec![];
+    let mut previous = Cell::Unknown;
+
+    for c in signal.wave.chars() {
+        let (cell, new) = match c {
+            '0' | 'l' | 'L' | 'd' => (Cell::Level(false), true),
+            '1' | 'h' | 'H' | 'u' => (Cell::Level(true), true),
+            'x' => (Cell::Unknown, true),
+            'z' => (Cell::HighZ, true),
+            'p' | 'P' => (Cell::Clock(true), true),
+            'n' | 'N' => (Cell::Clock(false), true),
+            '=' | '2'..='9' => (Cell::Data(data.next().map_or("", String::as_str)), true),
+            _ => (previous, false),
+        };
+        cells.push((cell, new));
+        for _ in 1..period {
+            cells.push((cell, false));
+        }
+        previous = cell;
+    }
+
+    cells
+}
+
+/// Converts the signals of a diagram to waveforms, each cycle lasting `period` time units
+pub fn stimulus(wave: &WaveJson, period: u32) -> Vec<ExpectedWave> {
+    wave.signals()
+        .into_iter()
+        .filter(|(_, _, signal)| !signal.wave.is_empty())
+        .map(|(_, name, signal)| {
+            let mut changes: Vec<(u32, String)> = vec![];
+            for (i, (cell, new)) in cells(signal).into_iter().enumerate() {
+                let time = i as u32 * period;
+                match cell {
+                    Cell::Clock(rising) => {
+                        let (first, second) = if rising { ("1", "0") } else { ("0", "1") };
+                        changes.push((time, first.to_owned()));
+                        changes.push((time + period / 2, second.to_owned()));
+                    }
+                    _ if !new => {}
+                    Cell::Level(level) => changes.push((time, (level as u8).to_string())),
+                    Cell::Unknown => changes.push((time, "x".to_owned())),
+                    Cell::HighZ => changes.push((time, "z".to_owned())),
+                    Cell::Data(label) => changes.push((time, label.to_owned())),
+                }
+            }
+            changes.dedup_by(|next, previous| next.1 == previous.1);
+
+            ExpectedWave { name, changes }
+        })
+        .collect()
+}
+
+/// Width of a cycle, in pixels
+const CYCLE_WIDTH: u32 = 40;
+const LANE_HEIGHT: u32 = 30;
+const SIGNAL_HEIGHT: u32 = 20;
+const CHAR_WIDTH: u32 = 7;
+const MARGIN: u32 = 10;
+
+/// Escapes text written in SVG
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a diagram as a standalone SVG document
+pub fn render_svg(wave: &WaveJson) -> String {
+    let signals = wave.signals();
+    let cycle = CYCLE_WIDTH
+        * wave
+            .config
+            .as_ref()
+            .and_then(|c| c.hscale)
+            .unwrap_or(1)
+            .max(1);
+    let names = signals
+        .iter()
+        .map(|(depth, _, s)| (s.name.len() + 2 * depth) as u32)
+        .max()
+        .unwrap_or(0)
+        * CHAR_WIDTH
+        + 2 * MARGIN;
+    let lanes = signals.iter().map(|(_, _, s)| cells(s)).collect::<Vec<_>>();
+    let cycles = lanes.iter().map(Vec::len).max().unwrap_or(0) as u32;
+
+    let head = wave.head.clone().unwrap_or_default();
+    let mut top = MARGIN;
+    if head.text.is_some() {
+        top += LANE_HEIGHT;
+    }
+    if head.tick.is_some() {
+        top += LANE_HEIGHT / 2;
+    }
+
+    let width = names + cycles * cycle + MARGIN;
+    let height = top + signals.len() as u32 * LANE_HEIGHT + MARGIN;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="monospace" font-size="11">
+<defs><pattern id="hatch" width="4" height="4" patternUnits="userSpaceOnUse" patternTransform="rotate(45)"><line x1="0" y1="0" x2="0" y2="4" stroke="#888" stroke-width="1"/></pattern></defs>
+<rect width="100%" height="100%" fill="white"/>"##
+    );
+
+    if let Some(text) = &head.text {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="14">{}</text>"#,
+            width / 2,
+            MARGIN + LANE_HEIGHT / 2,
+            escape(text)
+        );
+    }
+    for i in 0..cycles {
+        let x = names + i * cycle;
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x}" y1="{}" x2="{x}" y2="{}" stroke="#ddd" stroke-dasharray="2,2"/>"##,
+            top,
+            height - MARGIN
+        );
+        if let Some(tick) = head.tick {
+            let _ = writeln!(
+                svg,
+                r##"<text x="{x}" y="{}" text-anchor="middle" fill="#888">{}</text>"##,
+                top - 4,
+                tick + i
+            );
+        }
+    }
+
+    for (lane, ((depth, _, signal), cells)) in signals.iter().zip(&lanes).enumerate() {
+        let y = top + lane as u32 * LANE_HEIGHT + (LANE_HEIGHT - SIGNAL_HEIGHT) / 2;
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            MARGIN + 2 * *depth as u32 * CHAR_WIDTH,
+            y + SIGNAL_HEIGHT / 2 + 4,
+            escape(&signal.name)
+        );
+        render_lane(&mut svg, cells, names, y, cycle);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draws the cells of a lane whose top is at `y`
+fn render_lane(svg: &mut String, cells: &[(Cell, bool)], left: u32, y: u32, cycle: u32) {
+    let (high, low, middle) = (y, y + SIGNAL_HEIGHT, y + SIGNAL_HEIGHT / 2);
+    // Level at the end of the previous cell, to draw the transitions
+    let mut level = None;
+    let mut i = 0;
+
+    while i < cells.len() {
+        let cell = cells[i].0;
+        let mut end = i + 1;
+        while end < cells.len() && !cells[end].1 && !matches!(cell, Cell::Clock(_)) {
+            end += 1;
+        }
+        let (x0, x1) = (left + i as u32 * cycle, left + end as u32 * cycle);
+
+        match cell {
+            Cell::Level(value) => {
+                let y = if value { high } else { low };
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M{x0},{} L{x0},{y} L{x1},{y}" fill="none" stroke="black"/>"#,
+                    level.unwrap_or(y)
+                );
+                level = Some(y);
+            }
+            Cell::Clock(rising) => {
+                let (first, second) = if rising { (high, low) } else { (low, high) };
+                let half = x0 + cycle / 2;
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M{x0},{} L{x0},{first} L{half},{first} L{half},{second} L{x1},{second}" fill="none" stroke="black"/>"#,
+                    level.unwrap_or(second)
+                );
+                level = Some(second);
+            }
+            Cell::HighZ => {
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M{x0},{} L{x0},{middle} L{x1},{middle}" fill="none" stroke="blue"/>"#,
+                    level.unwrap_or(middle)
+                );
+                level = Some(middle);
+            }
+            Cell::Unknown | Cell::Data(_) => {
+                let fill = match cell {
+                    Cell::Unknown => "url(#hatch)",
+                    _ => "white",
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M{x0},{middle} L{},{high} L{},{high} L{x1},{middle} L{},{low} L{},{low} Z" fill="{fill}" stroke="black"/>"#,
+                    x0 + 3,
+                    x1 - 3,
+                    x1 - 3,
+                    x0 + 3
+                );
+                if let Cell::Data(label) = cell {
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                        (x0 + x1) / 2,
+                        middle + 4,
+                        escape(label)
+                    );
+                }
+                level = None;
+            }
+        }
+
+        i = end;
+    }
+}
+
+/// Converts a window of the `file`-th dump of the last simulation to a diagram
+#[tauri::command]
+pub fn wavedrom_from_vcd(
+    file: usize,
+    window: WaveWindow,
+    state: AppState<'_>,
+) -> Result<WaveJson, Error> {
+    let state = state.lock().unwrap();
+    let vcd = dump(&state, file)?;
+    let translations = match state.project() {
+        Some(project) => load_translations(project, vcd)?,
+        None => HashMap::new(),
+    };
+
+    from_vcd(vcd, &window, &translations)
+}
+
+#[tauri::command]
+pub fn read_wavedrom(path: String) -> Result<WaveJson, Error> {
+    WaveJson::parse(&fs::read_to_string(path)?)
+}
+
+/// Converts a diagram to waveforms, e.g. to compare a simulation with a lab handout
+#[tauri::command]
+pub fn wavedrom_stimulus(wave: WaveJson, period: u32) -> Vec<ExpectedWave> {
+    stimulus(&wave, period)
+}
+
+/// Writes a diagram to `path`, as an SVG image if its extension is `.svg` and as WaveJSON otherwise
+#[tauri::command]
+pub fn write_wavedrom(wave: WaveJson, path: String) -> Result<(), Error> {
+    let content = if Path::new(&path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+    {
+        render_svg(&wave)
+    } else {
+        serde_json::to_string_pretty(&wave)
+            .map_err(|e| Error::Other(format!("Could not serialize diagram: {e}")))?
+    };
+
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{icarus::vcd::test_dump, waves::format::Radix};
+
+    const HANDOUT: &str = r#"{signal: [
+  {name: 'clk', wave: 'p....'},
+  ['Bus', // Data lines
+    {name: 'data', wave: 'x=.=x', data: 'A B'},
+    {name: "req", wave: '01.0.',},
+  ],
+  {},
+], head: {text: 'Handout'}}"#;
+
+    #[test]
+    fn json5() {
+        let wave = WaveJson::parse(HANDOUT).unwrap();
+
+        assert_eq!(wave.head.unwrap().text.as_deref(), Some("Handout"));
+        assert_eq!(wave.signal.len(), 3);
+        let Lane::Group { name, lanes } = &wave.signal[1] else {
+            panic!("Expected a group");
+        };
+        assert_eq!(name, "Bus");
+        assert_eq!(
+            lanes[0],
+            Lane::Signal(WaveSignal {
+                name: "data".to_owned(),
+                wave: "x=.=x".to_owned(),
+                data: vec!["A".to_owned(), "B".to_owned()],
+                period: None
+            })
+        );
+        assert_eq!(wave.signal[2], Lane::Signal(WaveSignal::default()));
+    }
+
+    #[test]
+    fn stimulus_from_handout() {
+        let waves = stimulus(&WaveJson::parse(HANDOUT).unwrap(), 10);
+
+        assert_eq!(waves.len(), 3);
+        assert_eq!(waves[0].name, "clk");
+        assert_eq!(waves[0].changes.len(), 10);
+        assert_eq!(waves[0].changes[1], (5, "0".to_owned()));
+        assert_eq!(waves[1].name, "Bus.data");
+        assert_eq!(
+            waves[1].changes,
+            vec![
+                (0, "x".to_owned()),
+                (10, "A".to_owned()),
+                (30, "B".to_owned()),
+                (40, "x".to_owned())
+            ]
+        );
+        assert_eq!(
+            waves[2].changes,
+            vec![
+                (0, "0".to_owned()),
+                (10, "1".to_owned()),
+                (30, "0".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn vcd_window() {
+        let vcd = test_dump(
+            "top",
+            &["wire 1 ! clk", "wire 1 @ en", "wire 8 # data"],
+            r#"#0
+0!
+0@
+bx #
+#10
+1!
+#11
+1@
+b101 #
+#15
+0!
+#20
+1!
+#25
+0!
+#30
+1!
+#31
+0@
+b1111 #
+#35
+0!
+#40
+1!
+#45
+0!
+"#,
+        );
+
+        let column = |path: &str| Column {
+            source: Source::Signal {
+                path: path.to_owned(),
+            },
+            radix: Radix::Hexadecimal,
+        };
+        let window = WaveWindow {
+            columns: vec![column("top.en"), column("top.data")],
+            clock: "top.clk".to_owned(),
+            first_cycle: 0,
+            last_cycle: None,
+        };
+        let wave = from_vcd(&vcd, &window, &HashMap::new()).unwrap();
+
+        let signals = wave.signals();
+        assert_eq!(signals[0].2.wave, "p..");
+        assert_eq!(signals[1].2.wave, "1.0");
+        assert_eq!(signals[2].2.wave, "=.=");
+        assert_eq!(signals[2].2.data, vec!["05", "0f"]);
+
+        let svg = render_svg(&wave);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">0f</text>"));
+
+        let translations = HashMap::from([(
+            "top.data".to_owned(),
+            TranslationTable::from_filter_file("5 IDLE\nf BUSY\n"),
+        )]);
+        let wave = from_vcd(&vcd, &window, &translations).unwrap();
+        assert_eq!(wave.signals()[2].2.data, vec!["IDLE", "BUSY"]);
+    }
+}
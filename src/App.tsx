@@ -1,5 +1,6 @@
 import * as Dialog from "@radix-ui/react-dialog";
 import { invoke } from "@tauri-apps/api";
+import { listen } from "@tauri-apps/api/event";
 import MainScreen from "./components/MainScreen";
 import TaskBar from "./components/TaskBar";
 import { listenEvent, useEventBus } from "./main";
@@ -9,6 +10,28 @@ import { FileCreator, FileType } from "./components/FileDialog";
 
 type DialogType = "project" | "file";
 
+/** Starts a background job with `command`, resolving with its result once it is finished */
+async function runJob(command: string): Promise<any> {
+  // Listens before starting the job, which could finish before its id is returned
+  const finished = new Map<number, any>();
+  let waiting: ((job: any) => void) | null = null;
+  let id: number | null = null;
+  const unlisten = await listen("job-status", (event: any) => {
+    if (event.payload.status === "running") return;
+    finished.set(event.payload.id, event.payload);
+    if (event.payload.id === id && waiting) waiting(event.payload);
+  });
+
+  try {
+    id = (await invoke(command)) as number;
+    const job = finished.get(id) ?? (await new Promise((r) => (waiting = r)));
+    if (job.status === "succeeded") return job.result;
+    throw job.status === "failed" ? job.error : "Cancelled";
+  } finally {
+    unlisten();
+  }
+}
+
 function App() {
   const events = useEventBus();
   const [hasProject, setHasProject] = useState(false);
@@ -18,16 +41,16 @@ function App() {
     setCurrentDialog(visible ? dialogType : null);
 
   listenEvent("project.build", () => {
-    invoke("compile")
+    runJob("compile")
       .then((v) => events.emit("output.compilation", v))
       .catch((e) => console.error(e));
   });
   listenEvent("project.run", () => {
-    invoke("compile")
+    runJob("compile")
       .then((v: any) => {
         console.log(v)
         if (v.status === "success") {
-          invoke("simulate").then((v) => events.emit("output.simulation", v));
+          runJob("simulate").then((v) => events.emit("output.simulation", v));
         } else {
           events.emit("output.compilation", v);
         }